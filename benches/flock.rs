@@ -0,0 +1,34 @@
+//! time a step of the flock, with and without the compute task pool
+
+use bevy::math::Vec3;
+use boids::{Arena, Flock, FlockParams, SteeringBehaviors};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// `count` boids in an arena as crowded as the default one with 5000 boids,
+/// so that the boids have the same number of neighbors whatever the count
+fn flock(count: usize, parallel: bool, params: &FlockParams) -> Flock {
+    let mut flock = Flock::with_seed(0);
+    flock.arena = Arena::new(Vec3::new(1000., 500., 0.) * (count as f32 / 5000.).sqrt());
+    flock.parallel = parallel;
+    flock.add_random_boids(count, params);
+    flock
+}
+
+fn step(c: &mut Criterion) {
+    let params = FlockParams::default();
+    let behaviors = SteeringBehaviors::default();
+    let mut group = c.benchmark_group("step");
+    group.sample_size(10);
+    for count in [5_000, 20_000, 100_000] {
+        for (name, parallel) in [("serial", false), ("parallel", true)] {
+            let mut flock = flock(count, parallel, &params);
+            group.bench_with_input(BenchmarkId::new(name, count), &count, |b, _| {
+                b.iter(|| flock.step(&params, &behaviors, 1. / 60.))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step);
+criterion_main!(benches);
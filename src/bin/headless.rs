@@ -0,0 +1,77 @@
+//! run the flocking simulation without opening a window
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+use bevy::math::Vec3;
+use boids::cli::ParamOverrides;
+use boids::{Dimensions, Flock, FlockParams, SteeringBehaviors};
+use clap::Parser;
+
+const DT: f32 = 1. / 60.;
+
+#[derive(Parser)]
+#[command(about = "run the boids simulation without a window")]
+struct Cli {
+    /// number of simulation steps
+    #[arg(long, default_value_t = 1000)]
+    steps: usize,
+    /// number of boids
+    #[arg(long, default_value_t = 300)]
+    boids: usize,
+    /// number of predators hunting the flock
+    #[arg(long, default_value_t = 0)]
+    predators: usize,
+    /// fly in a plane or in a volume
+    #[arg(long, value_enum, default_value_t)]
+    dimensions: Dimensions,
+    /// steer the boids on a single thread
+    #[arg(long)]
+    serial: bool,
+    /// seed of the simulation, runs with the same seed are identical
+    #[arg(long)]
+    seed: Option<u64>,
+    /// read the flock parameters from a `.ron` or `.toml` file
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    #[command(flatten)]
+    overrides: ParamOverrides,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let params = match &cli.config {
+        Some(path) => FlockParams::load(path),
+        None => Ok(FlockParams::default()),
+    };
+    let params = params
+        .and_then(|params| cli.overrides.apply(params))
+        .unwrap_or_else(|e| {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        });
+    let (steps, count) = (cli.steps, cli.boids);
+
+    let mut flock = cli.seed.map_or_else(Flock::new, Flock::with_seed);
+    flock.arena = cli.dimensions.default_arena();
+    flock.parallel = !cli.serial;
+    flock.add_random_boids(count, &params);
+    flock.add_random_predators(cli.predators, &params);
+
+    let behaviors = SteeringBehaviors::default();
+    let start = Instant::now();
+    for _ in 0..steps {
+        flock.step(&params, &behaviors, DT);
+    }
+    let elapsed = start.elapsed();
+
+    let center = flock.boids.iter().map(|b| b.position).sum::<Vec3>() / count.max(1) as f32;
+    println!(
+        "{steps} steps of {count} boids in {:.3}s ({:.3}ms/step), center of the flock at ({:.1}, {:.1}, {:.1})",
+        elapsed.as_secs_f32(),
+        elapsed.as_secs_f32() * 1000. / steps.max(1) as f32,
+        center.x,
+        center.y,
+        center.z,
+    );
+}
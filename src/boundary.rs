@@ -0,0 +1,248 @@
+use bevy::math::Vec3;
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::flock::Boid;
+use crate::params::FlockParams;
+use crate::steering::{steer_towards, SteeringBehavior, Surroundings};
+
+/// what happens to boids at the edges of the arena
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum BoundaryMode {
+    /// leaving on one side enters on the opposite one, neighbors see across the edges
+    Wrap,
+    /// reflect off the edges like a ball
+    #[default]
+    Bounce,
+    /// steer back before reaching the edges, bounce as a last resort
+    Soft,
+    /// no edges at all
+    Open,
+}
+
+impl BoundaryMode {
+    /// the mode after this one, for cycling through them
+    pub fn next(self) -> Self {
+        match self {
+            BoundaryMode::Wrap => BoundaryMode::Bounce,
+            BoundaryMode::Bounce => BoundaryMode::Soft,
+            BoundaryMode::Soft => BoundaryMode::Open,
+            BoundaryMode::Open => BoundaryMode::Wrap,
+        }
+    }
+}
+
+/// whether the boids fly in a plane or in a volume
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Dimensions {
+    #[default]
+    Two,
+    Three,
+}
+
+impl Dimensions {
+    /// the arena used when no size is given
+    pub fn default_arena(self) -> Arena {
+        match self {
+            Dimensions::Two => Arena::default(),
+            Dimensions::Three => Arena::new(Vec3::new(1000., 500., 500.)),
+        }
+    }
+}
+
+/// the box the boids fly in, centered on the origin
+///
+/// An arena without depth is flat, the boids then stay at z = 0.
+/// As a resource it is copied into the flock whenever it changes.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct Arena {
+    pub half_size: Vec3,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::new(Vec3::new(1000., 500., 0.))
+    }
+}
+
+impl Arena {
+    /// an arena with the given width, height and depth
+    pub fn new(size: Vec3) -> Self {
+        Self {
+            half_size: size / 2.,
+        }
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.half_size.z <= 0.
+    }
+
+    /// number of axes the arena extends along
+    fn axes(&self) -> usize {
+        if self.is_flat() {
+            2
+        } else {
+            3
+        }
+    }
+
+    /// shortest vector from `from` to `to`, across the edges in wrap mode
+    pub fn offset(&self, mode: BoundaryMode, from: Vec3, to: Vec3) -> Vec3 {
+        let mut offset = to - from;
+        if mode == BoundaryMode::Wrap {
+            for axis in 0..self.axes() {
+                let size = self.half_size[axis] * 2.;
+                offset[axis] -= (offset[axis] / size).round() * size;
+            }
+        }
+        offset
+    }
+
+    /// bring a boid that just moved back into the arena according to `mode`
+    pub fn confine(&self, mode: BoundaryMode, boid: &mut Boid) {
+        for axis in 0..self.axes() {
+            let limit = self.half_size[axis];
+            let position = boid.position[axis];
+            match mode {
+                BoundaryMode::Wrap => {
+                    let wrapped = (position + limit).rem_euclid(2. * limit) - limit;
+                    boid.position[axis] = wrapped;
+                    // keep the interpolation from streaking across the arena
+                    boid.previous_position[axis] += wrapped - position;
+                }
+                // the clamps catch boids left far outside by a shrinking arena
+                BoundaryMode::Bounce | BoundaryMode::Soft => {
+                    if position > limit {
+                        boid.position[axis] = (2. * limit - position).max(-limit);
+                        boid.velocity[axis] = -boid.velocity[axis].abs();
+                    } else if position < -limit {
+                        boid.position[axis] = (-2. * limit - position).min(limit);
+                        boid.velocity[axis] = boid.velocity[axis].abs();
+                    }
+                }
+                BoundaryMode::Open => {}
+            }
+        }
+    }
+}
+
+/// in soft boundary mode, steer back towards the inside of the arena when
+/// closer than `FlockParams::edge_margin` to an edge
+pub struct EdgeAvoidance;
+
+impl SteeringBehavior for EdgeAvoidance {
+    fn steer(&self, boid: &Boid, _neighbors: &[Boid], surroundings: &Surroundings) -> Vec3 {
+        let params = surroundings.params;
+        if params.boundary != BoundaryMode::Soft || params.edge_margin <= 0. {
+            return Vec3::ZERO;
+        }
+        let arena = surroundings.arena;
+        // how deep the boid is in the margin along each axis
+        let mut depth = (boid.position.abs() - (arena.half_size - params.edge_margin))
+            .max(Vec3::ZERO)
+            / params.edge_margin;
+        if arena.is_flat() {
+            depth.z = 0.;
+        }
+        let inward = -boid.position.signum() * depth;
+        if inward == Vec3::ZERO {
+            return Vec3::ZERO;
+        }
+        steer_towards(inward, boid.velocity, params) * depth.max_element().min(1.)
+    }
+
+    fn weight(&self, params: &FlockParams) -> f32 {
+        params.edge_weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounce_reflects_about_the_normal() {
+        let arena = Arena::default();
+        let mut boid = Boid::new(Vec3::new(510., 0., 0.), Vec3::new(30., 40., 0.));
+        arena.confine(BoundaryMode::Bounce, &mut boid);
+        assert_eq!(boid.position, Vec3::new(490., 0., 0.));
+        assert_eq!(boid.velocity, Vec3::new(-30., 40., 0.));
+    }
+
+    #[test]
+    fn bounce_brings_back_boids_far_outside() {
+        let arena = Arena::default();
+        let mut boid = Boid::new(Vec3::new(0., 900., 0.), Vec3::Y);
+        arena.confine(BoundaryMode::Bounce, &mut boid);
+        assert_eq!(boid.position, Vec3::new(0., -250., 0.));
+        assert_eq!(boid.velocity, Vec3::NEG_Y);
+    }
+
+    #[test]
+    fn wrap_moves_to_the_opposite_side() {
+        let arena = Arena::default();
+        let mut boid = Boid::new(Vec3::new(0., -260., 0.), Vec3::NEG_Y);
+        boid.previous_position = Vec3::new(0., -245., 0.);
+        arena.confine(BoundaryMode::Wrap, &mut boid);
+        assert_eq!(boid.position, Vec3::new(0., 240., 0.));
+        assert_eq!(boid.previous_position, Vec3::new(0., 255., 0.));
+        assert_eq!(boid.velocity, Vec3::NEG_Y);
+    }
+
+    #[test]
+    fn flat_arenas_leave_the_depth_alone() {
+        let flat = Arena::default();
+        let mut boid = Boid::new(Vec3::new(0., 0., 5.), Vec3::Z);
+        flat.confine(BoundaryMode::Wrap, &mut boid);
+        assert_eq!(boid.position, Vec3::new(0., 0., 5.));
+
+        let volume = Dimensions::Three.default_arena();
+        volume.confine(BoundaryMode::Wrap, &mut boid);
+        boid.position.z = 260.;
+        volume.confine(BoundaryMode::Wrap, &mut boid);
+        assert_eq!(boid.position, Vec3::new(0., 0., -240.));
+    }
+
+    #[test]
+    fn wrap_offsets_take_the_short_way() {
+        let arena = Arena::default();
+        let from = Vec3::new(495., 0., 0.);
+        let to = Vec3::new(-495., 10., 0.);
+        assert_eq!(
+            arena.offset(BoundaryMode::Wrap, from, to),
+            Vec3::new(10., 10., 0.)
+        );
+        assert_eq!(
+            arena.offset(BoundaryMode::Bounce, from, to),
+            Vec3::new(-990., 10., 0.)
+        );
+    }
+
+    #[test]
+    fn open_mode_lets_boids_go() {
+        let arena = Arena::default();
+        let mut boid = Boid::new(Vec3::new(1000., 0., 0.), Vec3::X);
+        arena.confine(BoundaryMode::Open, &mut boid);
+        assert_eq!(boid.position, Vec3::new(1000., 0., 0.));
+    }
+
+    #[test]
+    fn soft_mode_steers_inwards_near_the_edges() {
+        let params = FlockParams {
+            boundary: BoundaryMode::Soft,
+            ..Default::default()
+        };
+        let arena = Arena::default();
+        let surroundings = Surroundings {
+            params: &params,
+            arena: &arena,
+            obstacles: &[],
+            predators: &[],
+            lure: None,
+        };
+        let near_edge = Boid::new(Vec3::new(495., 0., 0.), Vec3::X * params.max_speed);
+        assert!(EdgeAvoidance.steer(&near_edge, &[], &surroundings).x < 0.);
+        let center = Boid::new(Vec3::ZERO, Vec3::X * params.max_speed);
+        assert_eq!(EdgeAvoidance.steer(&center, &[], &surroundings), Vec3::ZERO);
+    }
+}
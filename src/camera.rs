@@ -0,0 +1,196 @@
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::boundary::Arena;
+use crate::flock::Boid;
+use crate::pointer::Pointer;
+
+/// the boid picked with ctrl + click
+#[derive(Component)]
+pub struct Selected;
+
+/// how close to a boid a click has to be to select it
+const SELECT_RADIUS: f32 = 20.;
+/// how fast the camera catches up with the followed boid, per second
+const FOLLOW_RATE: f32 = 5.;
+/// bounds of the scale of the camera projection, smaller zooms in
+const MIN_SCALE: f32 = 0.05;
+const MAX_SCALE: f32 = 10.;
+/// scroll of a trackpad that counts as one notch of a mouse wheel
+const PIXELS_PER_LINE: f32 = 20.;
+
+/// factor scaling the view for the scrolled wheel or trackpad, below 1 when
+/// scrolling up
+pub(crate) fn zoom_factor<'a>(wheel: impl IntoIterator<Item = &'a MouseWheel>) -> f32 {
+    let lines: f32 = wheel
+        .into_iter()
+        .map(|w| match w.unit {
+            MouseScrollUnit::Line => w.y,
+            MouseScrollUnit::Pixel => w.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    (-lines * 0.1).exp()
+}
+
+/// whether the camera keeps the selected boid in the middle
+#[derive(Resource, Default)]
+struct Following(bool);
+
+/// ctrl + click selects the boid under the cursor and follows it, F toggles
+/// following, escape drops the selection
+fn select_boid(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    pointer: Res<Pointer>,
+    boids: Query<(Entity, &Boid)>,
+    selected: Query<Entity, With<Selected>>,
+    mut following: ResMut<Following>,
+    mut commands: Commands,
+) {
+    if keys.just_pressed(KeyCode::F) {
+        following.0 = !following.0;
+    }
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let click = ctrl && buttons.just_pressed(MouseButton::Left);
+    if !click && !keys.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    for entity in &selected {
+        commands.entity(entity).remove::<Selected>();
+    }
+    following.0 = false;
+    let Some(cursor) = pointer.filter(|_| click) else {
+        return;
+    };
+    let nearest = boids
+        .iter()
+        .map(|(entity, boid)| (entity, boid.position.distance(cursor)))
+        .filter(|&(_, distance)| distance <= SELECT_RADIUS)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+    if let Some((entity, _)) = nearest {
+        commands.entity(entity).insert(Selected);
+        following.0 = true;
+    }
+}
+
+/// dragging with the middle mouse button pans the camera and stops following,
+/// the wheel zooms
+fn pan_and_zoom(
+    buttons: Res<Input<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut cameras: Query<(&Camera, &mut Transform, &mut OrthographicProjection)>,
+    mut following: ResMut<Following>,
+) {
+    let drag: Vec2 = motion.read().map(|m| m.delta).sum();
+    let zoom = zoom_factor(wheel.read());
+    for (camera, mut transform, mut projection) in &mut cameras {
+        if buttons.pressed(MouseButton::Middle) && drag != Vec2::ZERO {
+            if let Some(viewport) = camera.logical_viewport_size() {
+                let units_per_pixel = projection.area.width() / viewport.x;
+                transform.translation += Vec3::new(-drag.x, drag.y, 0.) * units_per_pixel;
+                following.0 = false;
+            }
+        }
+        projection.scale = (projection.scale * zoom).clamp(MIN_SCALE, MAX_SCALE);
+    }
+}
+
+/// move the camera smoothly towards where the followed boid is drawn
+fn follow_selected(
+    time: Res<Time<Real>>,
+    following: Res<Following>,
+    arena: Res<Arena>,
+    selected: Query<&Transform, (With<Selected>, Without<Camera>)>,
+    mut cameras: Query<&mut Transform, With<Camera>>,
+) {
+    let Ok(target) = selected.get_single() else {
+        return;
+    };
+    if !following.0 {
+        return;
+    }
+    let t = 1. - (-FOLLOW_RATE * time.delta_seconds()).exp();
+    let jump = arena.half_size.truncate().min_element();
+    for mut transform in &mut cameras {
+        let target = target
+            .translation
+            .truncate()
+            .extend(transform.translation.z);
+        // the boid wrapped around the arena, catching up would sweep over all of it
+        if transform.translation.distance(target) > jump {
+            transform.translation = target;
+        } else {
+            transform.translation = transform.translation.lerp(target, t);
+        }
+    }
+}
+
+/// pan, zoom and follow a selected boid with the 2D camera
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Following>()
+            .add_systems(Update, (select_boid, pan_and_zoom).chain())
+            .add_systems(
+                PostUpdate,
+                follow_selected.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn ctrl_click_selects_the_nearest_boid() {
+        let mut world = World::new();
+        let mut keys = Input::<KeyCode>::default();
+        keys.press(KeyCode::ControlLeft);
+        let mut buttons = Input::<MouseButton>::default();
+        buttons.press(MouseButton::Left);
+        world.insert_resource(keys);
+        world.insert_resource(buttons);
+        world.insert_resource(Pointer(Some(Vec3::new(10., 0., 0.))));
+        world.init_resource::<Following>();
+        let near = world.spawn(Boid::new(Vec3::new(15., 0., 0.), Vec3::X)).id();
+        let far = world.spawn(Boid::new(Vec3::ZERO, Vec3::X)).id();
+        world.spawn(Boid::new(Vec3::new(100., 0., 0.), Vec3::X));
+
+        world.run_system_once(select_boid);
+        assert!(world.get::<Selected>(near).is_some());
+        assert!(world.get::<Selected>(far).is_none());
+        assert!(world.resource::<Following>().0);
+
+        world.resource_mut::<Input<MouseButton>>().reset_all();
+        world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Escape);
+        world.run_system_once(select_boid);
+        assert!(world.get::<Selected>(near).is_none());
+        assert!(!world.resource::<Following>().0);
+    }
+
+    #[test]
+    fn trackpads_zoom_as_smoothly_as_wheels() {
+        let scroll = |unit, y| MouseWheel {
+            unit,
+            x: 0.,
+            y,
+            window: Entity::PLACEHOLDER,
+        };
+        let notch = zoom_factor(&[scroll(MouseScrollUnit::Line, 1.)]);
+        assert!(notch > 0.8 && notch < 1.);
+        let swipe = zoom_factor(&[
+            scroll(MouseScrollUnit::Pixel, 30.),
+            scroll(MouseScrollUnit::Pixel, 50.),
+        ]);
+        assert!(swipe > 0.5 && swipe < notch);
+        assert!(zoom_factor(&[scroll(MouseScrollUnit::Line, -1.)]) > 1.);
+    }
+}
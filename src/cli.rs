@@ -0,0 +1,90 @@
+use bevy::prelude::Resource;
+use clap::Args;
+
+use crate::boundary::BoundaryMode;
+use crate::params::{FlockParams, ParamsError};
+
+/// command line flags overriding single flock parameters
+///
+/// Kept as a resource so that the overrides survive a reload of the config file.
+#[derive(Args, Resource, Clone, Debug, Default)]
+pub struct ParamOverrides {
+    #[arg(long)]
+    pub min_speed: Option<f32>,
+    #[arg(long)]
+    pub max_speed: Option<f32>,
+    #[arg(long)]
+    pub max_force: Option<f32>,
+    #[arg(long)]
+    pub neighbor_distance: Option<f32>,
+    /// in radians
+    #[arg(long)]
+    pub neighbor_angle: Option<f32>,
+    #[arg(long)]
+    pub separation_distance: Option<f32>,
+    #[arg(long)]
+    pub separation_weight: Option<f32>,
+    #[arg(long)]
+    pub alignment_weight: Option<f32>,
+    #[arg(long)]
+    pub cohesion_weight: Option<f32>,
+    #[arg(long)]
+    pub obstacle_look_ahead: Option<f32>,
+    #[arg(long)]
+    pub obstacle_weight: Option<f32>,
+    #[arg(long)]
+    pub predator_speed: Option<f32>,
+    #[arg(long)]
+    pub panic_distance: Option<f32>,
+    #[arg(long)]
+    pub flee_weight: Option<f32>,
+    #[arg(long, value_enum)]
+    pub boundary: Option<BoundaryMode>,
+    #[arg(long)]
+    pub edge_margin: Option<f32>,
+    #[arg(long)]
+    pub edge_weight: Option<f32>,
+    #[arg(long)]
+    pub lure_radius: Option<f32>,
+    #[arg(long)]
+    pub lure_weight: Option<f32>,
+    #[arg(long)]
+    pub species_weight: Option<f32>,
+}
+
+impl ParamOverrides {
+    /// replace the parameters given on the command line, and check the result
+    pub fn apply(&self, mut params: FlockParams) -> Result<FlockParams, ParamsError> {
+        let overrides = [
+            (self.min_speed, &mut params.min_speed),
+            (self.max_speed, &mut params.max_speed),
+            (self.max_force, &mut params.max_force),
+            (self.neighbor_distance, &mut params.neighbor_distance),
+            (self.neighbor_angle, &mut params.neighbor_angle),
+            (self.separation_distance, &mut params.separation_distance),
+            (self.separation_weight, &mut params.separation_weight),
+            (self.alignment_weight, &mut params.alignment_weight),
+            (self.cohesion_weight, &mut params.cohesion_weight),
+            (self.obstacle_look_ahead, &mut params.obstacle_look_ahead),
+            (self.obstacle_weight, &mut params.obstacle_weight),
+            (self.predator_speed, &mut params.predator_speed),
+            (self.panic_distance, &mut params.panic_distance),
+            (self.flee_weight, &mut params.flee_weight),
+            (self.edge_margin, &mut params.edge_margin),
+            (self.edge_weight, &mut params.edge_weight),
+            (self.lure_radius, &mut params.lure_radius),
+            (self.lure_weight, &mut params.lure_weight),
+            (self.species_weight, &mut params.species_weight),
+        ];
+        for (value, field) in overrides {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+        if let Some(boundary) = self.boundary {
+            params.boundary = boundary;
+        }
+        params.validate()?;
+        Ok(params)
+    }
+}
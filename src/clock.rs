@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+/// speed multipliers of the simulation, from slowest to fastest
+const SPEEDS: [f32; 7] = [0.1, 0.25, 0.5, 1., 2., 5., 10.];
+
+/// pausing, single stepping and speed of the simulation; only the flock
+/// freezes or speeds up, rendering and input keep running
+#[derive(Resource)]
+pub struct SimulationClock {
+    pub paused: bool,
+    /// ticks still to run while paused
+    steps: u32,
+    /// how much faster than real time the simulation runs
+    pub speed: f32,
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            steps: 0,
+            speed: 1.,
+        }
+    }
+}
+
+impl SimulationClock {
+    /// run one more tick while paused
+    pub fn step(&mut self) {
+        if self.paused {
+            self.steps += 1;
+        }
+    }
+
+    /// whether the flock moves in the current tick
+    pub fn tick(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+        if self.steps == 0 {
+            return false;
+        }
+        self.steps -= 1;
+        true
+    }
+
+    /// switch to the next higher speed, if any
+    pub fn faster(&mut self) {
+        if let Some(&speed) = SPEEDS.iter().find(|&&s| s > self.speed) {
+            self.speed = speed;
+        }
+    }
+
+    /// switch to the next lower speed, if any
+    pub fn slower(&mut self) {
+        if let Some(&speed) = SPEEDS.iter().rev().find(|&&s| s < self.speed) {
+            self.speed = speed;
+        }
+    }
+}
+
+/// space pauses, the period key runs a single tick while paused, plus and
+/// minus change the speed and backspace resets it
+fn control_clock(keys: Res<Input<KeyCode>>, mut clock: ResMut<SimulationClock>) {
+    if keys.just_pressed(KeyCode::Space) {
+        clock.paused = !clock.paused;
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        clock.step();
+    }
+    if keys.any_just_pressed([KeyCode::Equals, KeyCode::Plus, KeyCode::NumpadAdd]) {
+        clock.faster();
+    }
+    if keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        clock.slower();
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        clock.speed = 1.;
+    }
+}
+
+/// the fixed ticks keep their length, a faster clock runs more of them per frame
+fn apply_speed(clock: Res<SimulationClock>, mut time: ResMut<Time<Virtual>>) {
+    if clock.is_changed() && time.relative_speed() != clock.speed {
+        time.set_relative_speed(clock.speed);
+    }
+}
+
+/// the `SimulationClock` and its key bindings
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationClock>()
+            .add_systems(Update, (control_clock, apply_speed).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_clock_only_runs_the_requested_steps() {
+        let mut clock = SimulationClock::default();
+        clock.step();
+        assert!(clock.tick() && clock.tick());
+        clock.paused = true;
+        clock.step();
+        clock.step();
+        assert!(clock.tick() && clock.tick());
+        assert!(!clock.tick());
+    }
+
+    #[test]
+    fn speed_stays_between_the_slowest_and_the_fastest() {
+        let mut clock = SimulationClock::default();
+        clock.faster();
+        assert_eq!(clock.speed, 2.);
+        for _ in 0..SPEEDS.len() {
+            clock.faster();
+        }
+        assert_eq!(clock.speed, 10.);
+        for _ in 0..SPEEDS.len() {
+            clock.slower();
+        }
+        assert_eq!(clock.speed, 0.1);
+    }
+}
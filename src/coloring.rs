@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+
+use crate::flock::Boid;
+use crate::params::FlockParams;
+
+/// neighbor count shown with the warmest color
+const CROWDED: f32 = 12.;
+
+/// what the colors of the boids show; the 3D view only shows the species
+/// colors of `Plain`
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorMode {
+    /// the color of their species
+    #[default]
+    Plain,
+    /// the direction they fly in, around the color wheel
+    Heading,
+    /// how many neighbors they see, from blue for none to red for a crowd
+    Neighbors,
+    /// from blue at the minimum speed to red at the maximum
+    Speed,
+    /// a color for every group of boids connected through neighbors
+    Cluster,
+}
+
+impl ColorMode {
+    /// the mode after this one, for cycling through them
+    pub fn next(self) -> Self {
+        match self {
+            ColorMode::Plain => ColorMode::Heading,
+            ColorMode::Heading => ColorMode::Neighbors,
+            ColorMode::Neighbors => ColorMode::Speed,
+            ColorMode::Speed => ColorMode::Cluster,
+            ColorMode::Cluster => ColorMode::Plain,
+        }
+    }
+}
+
+/// from blue for 0 to red for 1
+fn gradient(t: f32) -> Color {
+    Color::hsl(240. * (1. - t.clamp(0., 1.)), 0.8, 0.55)
+}
+
+/// what a boid saw at the last tick, written by `step_boids` for the colors
+/// that depend on the rest of the flock
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Neighborhood {
+    /// number of neighbors
+    pub count: usize,
+    /// label shared by the boids connected through neighbors, see
+    /// `Flock::clusters`; only kept up to date in the `Cluster` mode
+    pub cluster: usize,
+}
+
+/// color of `boid` in the given mode
+pub fn boid_color(
+    mode: ColorMode,
+    boid: &Boid,
+    neighborhood: &Neighborhood,
+    params: &FlockParams,
+) -> Color {
+    match mode {
+        ColorMode::Plain => params.species_params(boid.species).color(),
+        ColorMode::Heading => {
+            let angle = boid.velocity.y.atan2(boid.velocity.x).to_degrees();
+            Color::hsl(angle.rem_euclid(360.), 0.8, 0.6)
+        }
+        ColorMode::Neighbors => gradient(neighborhood.count as f32 / CROWDED),
+        ColorMode::Speed => {
+            let range = (params.max_speed - params.min_speed).max(f32::EPSILON);
+            gradient((boid.velocity.length() - params.min_speed) / range)
+        }
+        // the golden angle keeps the hues of close labels apart
+        ColorMode::Cluster => Color::hsl(
+            (neighborhood.cluster as f32 * 137.508).rem_euclid(360.),
+            0.8,
+            0.6,
+        ),
+    }
+}
+
+fn add_neighborhoods(boids: Query<Entity, Added<Boid>>, mut commands: Commands) {
+    for entity in &boids {
+        commands.entity(entity).insert(Neighborhood::default());
+    }
+}
+
+/// switch to the next color mode with the V key
+fn cycle_colors(keys: Res<Input<KeyCode>>, mut mode: ResMut<ColorMode>) {
+    if keys.just_pressed(KeyCode::V) {
+        *mode = mode.next();
+        info!("color mode: {:?}", *mode);
+    }
+}
+
+/// the `ColorMode`, its key binding and the `Neighborhood` of the boids
+pub struct ColoringPlugin;
+
+impl Plugin for ColoringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColorMode>()
+            .add_systems(Update, (add_neighborhoods, cycle_colors));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::species::{Species, SpeciesParams};
+
+    #[test]
+    fn colors_follow_the_boid() {
+        let params = FlockParams {
+            species: vec![
+                SpeciesParams::default(),
+                SpeciesParams {
+                    color: [1., 0., 0.],
+                    ..default()
+                },
+            ],
+            ..default()
+        };
+        let slow = Boid::new(Vec3::ZERO, Vec3::Y * params.min_speed);
+        let fast = Boid {
+            species: Species(1),
+            ..Boid::new(Vec3::X * 300., Vec3::NEG_X * params.max_speed)
+        };
+        let alone = Neighborhood::default();
+        let crowded = Neighborhood {
+            count: 20,
+            cluster: 1,
+        };
+        let color = |mode, boid, neighborhood| boid_color(mode, boid, neighborhood, &params);
+
+        assert_eq!(color(ColorMode::Plain, &fast, &alone), Color::RED);
+        assert_ne!(
+            color(ColorMode::Heading, &slow, &alone),
+            color(ColorMode::Heading, &fast, &alone)
+        );
+        assert_eq!(color(ColorMode::Speed, &slow, &alone), gradient(0.));
+        assert_eq!(color(ColorMode::Speed, &fast, &alone), gradient(1.));
+        assert_eq!(color(ColorMode::Neighbors, &slow, &crowded), gradient(1.));
+        assert_ne!(
+            color(ColorMode::Cluster, &slow, &alone),
+            color(ColorMode::Cluster, &slow, &crowded)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+
+use crate::cli::ParamOverrides;
+use crate::params::{FlockParams, ParamsError};
+
+/// loads `FlockParams` from `.ron` and `.toml` files
+#[derive(Default)]
+pub struct FlockParamsLoader;
+
+impl AssetLoader for FlockParamsLoader {
+    type Asset = FlockParams;
+    type Settings = ();
+    type Error = ParamsError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<FlockParams, ParamsError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let extension = load_context
+                .path()
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            FlockParams::parse(std::str::from_utf8(&bytes)?, extension)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron", "toml"]
+    }
+}
+
+/// handle to the config file the flock parameters are read from
+#[derive(Resource)]
+struct FlockConfig(Handle<FlockParams>);
+
+/// watches a config file in the assets folder and copies it into the
+/// `FlockParams` resource whenever it is (re)loaded
+pub struct FlockConfigPlugin {
+    /// path of the config file, relative to the assets folder
+    pub path: String,
+}
+
+impl Plugin for FlockConfigPlugin {
+    fn build(&self, app: &mut App) {
+        let path = self.path.clone();
+        app.init_asset::<FlockParams>()
+            .init_asset_loader::<FlockParamsLoader>()
+            .init_resource::<FlockParams>()
+            .add_systems(
+                Startup,
+                move |mut commands: Commands, asset_server: Res<AssetServer>| {
+                    commands.insert_resource(FlockConfig(asset_server.load(&path)));
+                },
+            )
+            .add_systems(PreUpdate, apply_config);
+    }
+}
+
+/// replace the flock parameters with the freshly loaded config file; invalid
+/// ones are reported and the previous parameters kept
+fn apply_config(
+    mut events: EventReader<AssetEvent<FlockParams>>,
+    configs: Res<Assets<FlockParams>>,
+    config: Option<Res<FlockConfig>>,
+    overrides: Option<Res<ParamOverrides>>,
+    mut params: ResMut<FlockParams>,
+) {
+    let Some(config) = config else {
+        return;
+    };
+    for event in events.read() {
+        if !(event.is_loaded_with_dependencies(&config.0) || event.is_modified(&config.0)) {
+            continue;
+        }
+        let Some(loaded) = configs.get(&config.0) else {
+            continue;
+        };
+        let loaded = loaded.clone();
+        let loaded = match &overrides {
+            Some(overrides) => overrides.apply(loaded),
+            None => Ok(loaded),
+        };
+        match loaded {
+            Ok(loaded) => {
+                *params = loaded;
+                info!("flock parameters loaded: {:?}", *params);
+            }
+            Err(e) => error!("{e}, keeping the previous flock parameters"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_file_is_applied_with_overrides() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            FlockConfigPlugin {
+                path: "flock.ron".to_owned(),
+            },
+        ))
+        .insert_resource(ParamOverrides {
+            max_speed: Some(170.),
+            ..default()
+        });
+
+        for _ in 0..200 {
+            app.update();
+            if app.world.resource::<FlockParams>().max_speed == 170. {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let params = app.world.resource::<FlockParams>();
+        assert_eq!(params.max_speed, 170.);
+        assert_eq!(
+            params.cohesion_weight,
+            FlockParams::default().cohesion_weight
+        );
+    }
+
+    #[test]
+    fn edited_config_is_applied_with_overrides() {
+        let dir = std::env::temp_dir().join(format!("boids-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("edited.ron"), "(cohesion_weight: 2.)").unwrap();
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                file_path: dir.to_string_lossy().into_owned(),
+                ..default()
+            },
+            FlockConfigPlugin {
+                path: "edited.ron".to_owned(),
+            },
+        ))
+        .insert_resource(ParamOverrides {
+            max_speed: Some(170.),
+            ..default()
+        });
+        for _ in 0..200 {
+            app.update();
+            if app.world.resource::<FlockParams>().cohesion_weight == 2. {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(app.world.resource::<FlockParams>().cohesion_weight, 2.);
+
+        // what the file watcher does when the file is saved
+        let handle = app.world.resource::<FlockConfig>().0.clone();
+        let edit = |app: &mut App, params: FlockParams| {
+            *app.world
+                .resource_mut::<Assets<FlockParams>>()
+                .get_mut(&handle)
+                .unwrap() = params;
+            // the event is sent at the end of one frame and read in the next
+            app.update();
+            app.update();
+        };
+        edit(
+            &mut app,
+            FlockParams {
+                cohesion_weight: 3.,
+                ..default()
+            },
+        );
+        let params = app.world.resource::<FlockParams>();
+        assert_eq!(params.cohesion_weight, 3.);
+        assert_eq!(params.max_speed, 170.);
+
+        // above the max speed of the overrides, so the edit is dropped
+        edit(
+            &mut app,
+            FlockParams {
+                cohesion_weight: 4.,
+                min_speed: 200.,
+                max_speed: 300.,
+                ..default()
+            },
+        );
+        let params = app.world.resource::<FlockParams>();
+        assert_eq!(params.cohesion_weight, 3.);
+        assert_eq!(params.min_speed, FlockParams::default().min_speed);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,494 @@
+use bevy::ecs::component::Component;
+use bevy::math::{Quat, Vec3};
+use bevy::tasks::{ComputeTaskPool, ParallelSlice, TaskPool};
+use rand::prelude::*;
+
+use crate::boundary::{Arena, BoundaryMode};
+use crate::grid::{NeighborScratch, SpatialGrid};
+use crate::lure::Lure;
+use crate::obstacle::Obstacle;
+use crate::params::FlockParams;
+use crate::predator::Predator;
+use crate::species::{Relation, Species};
+use crate::steering::{SteeringBehaviors, Surroundings};
+
+/// a single boid; in the app, a component of the boid entities
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Boid {
+    pub position: Vec3,
+    /// units per second, the boid always faces along it
+    pub velocity: Vec3,
+    /// position before the last step, for interpolated rendering
+    pub previous_position: Vec3,
+    /// velocity before the last step, for interpolated rendering
+    pub previous_velocity: Vec3,
+    pub species: Species,
+}
+
+impl Boid {
+    pub fn new(position: Vec3, velocity: Vec3) -> Self {
+        Self {
+            position,
+            velocity,
+            previous_position: position,
+            previous_velocity: velocity,
+            species: Species::default(),
+        }
+    }
+
+    /// rotation of the boid sprite, pointing `BASE_DIRECTION` along the velocity
+    pub fn rotation(&self) -> Quat {
+        heading(self.velocity)
+    }
+
+    /// remember the current state before it is changed by a step
+    pub fn save_previous(&mut self) {
+        self.previous_position = self.position;
+        self.previous_velocity = self.velocity;
+    }
+
+    /// fly along the velocity for `dt` seconds, see `Arena::confine` for the walls
+    pub fn advance(&mut self, dt: f32) {
+        self.position += self.velocity * dt;
+    }
+
+    /// position and rotation at `alpha` (between 0 and 1) of the way from
+    /// the previous step to the current one
+    pub fn interpolated(&self, alpha: f32) -> (Vec3, Quat) {
+        (
+            self.previous_position.lerp(self.position, alpha),
+            heading(self.previous_velocity).slerp(self.rotation(), alpha),
+        )
+    }
+}
+
+pub const BASE_DIRECTION: Vec3 = Vec3::new(0., 1., 0.);
+
+/// rotation taking `BASE_DIRECTION` to the direction of `velocity`
+fn heading(velocity: Vec3) -> Quat {
+    match velocity.try_normalize() {
+        Some(direction) => Quat::from_rotation_arc(BASE_DIRECTION, direction),
+        None => Quat::IDENTITY,
+    }
+}
+
+/// check if two boids are neighbors according to the distance and angle criteria
+pub fn is_neighbor(me: &Boid, other: &Boid, params: &FlockParams) -> bool {
+    !ignores(me, other, params) && sees(me, other.position - me.position, params)
+}
+
+/// check if the species of `me` ignores the one of `other`, wherever it is
+pub fn ignores(me: &Boid, other: &Boid, params: &FlockParams) -> bool {
+    params.relation(me.species, other.species) == Relation::Ignore
+}
+
+/// check if a boid at `direction` from `me` is a neighbor of `me`
+pub fn sees(me: &Boid, direction: Vec3, params: &FlockParams) -> bool {
+    // Check distance criterion
+    if direction.length_squared() >= params.neighbor_distance_squared() {
+        return false;
+    }
+
+    // Calculate the angle between the heading and the other boid
+    let angle = me.velocity.angle_between(direction);
+
+    // Check angle criterion
+    angle < params.neighbor_angle
+}
+
+/// neighbors of `me` among all of `boids`, without a grid; like
+/// `SpatialGrid::neighbors`, they are moved next to `me` across a wrapping edge
+pub fn neighbors_among<'a>(
+    me: &Boid,
+    boids: impl IntoIterator<Item = &'a Boid>,
+    arena: &Arena,
+    params: &FlockParams,
+) -> Vec<Boid> {
+    boids
+        .into_iter()
+        .filter_map(|other| {
+            let offset = arena.offset(params.boundary, me.position, other.position);
+            (!ignores(me, other, params) && sees(me, offset, params)).then(|| Boid {
+                position: me.position + offset,
+                ..*other
+            })
+        })
+        .collect()
+}
+
+/// a flock of boids, independent of any rendering
+///
+/// All the randomness of the simulation comes from the flock's own generator,
+/// so two flocks created with the same seed evolve identically.
+pub struct Flock {
+    pub boids: Vec<Boid>,
+    pub obstacles: Vec<Obstacle>,
+    pub predators: Vec<Predator>,
+    pub arena: Arena,
+    /// point the boids are drawn to or driven away from, e.g. by the mouse
+    pub lure: Option<Lure>,
+    /// whether the steering of the boids is computed on the `ComputeTaskPool`
+    pub parallel: bool,
+    /// how many neighbors every boid saw in the last step, in the order of `boids`
+    pub neighbor_counts: Vec<usize>,
+    grid: SpatialGrid,
+    rng: StdRng,
+}
+
+impl Default for Flock {
+    fn default() -> Self {
+        Self::with_rng(StdRng::from_entropy())
+    }
+}
+
+impl Flock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(rng: StdRng) -> Self {
+        Self {
+            boids: Vec::new(),
+            obstacles: Vec::new(),
+            predators: Vec::new(),
+            arena: Arena::default(),
+            lure: None,
+            parallel: true,
+            neighbor_counts: Vec::new(),
+            grid: SpatialGrid::default(),
+            rng,
+        }
+    }
+
+    /// a boid somewhere in the arena with a random heading and a speed in the given range
+    fn random_boid(&mut self, min_speed: f32, max_speed: f32) -> Boid {
+        let rng = &mut self.rng;
+        let half_size = self.arena.half_size;
+        let pos_x = half_size.x - rng.gen::<f32>() * 2. * half_size.x;
+        let pos_y = half_size.y - rng.gen::<f32>() * 2. * half_size.y;
+        let angle = rng.gen::<f32>() * std::f32::consts::TAU;
+        let speed = min_speed + rng.gen::<f32>() * (max_speed - min_speed);
+
+        let mut position = Vec3::new(pos_x, pos_y, 0.);
+        let mut direction = Quat::from_rotation_z(angle).mul_vec3(BASE_DIRECTION);
+        if !self.arena.is_flat() {
+            position.z = half_size.z - rng.gen::<f32>() * 2. * half_size.z;
+            // tilt out of the plane so that the headings are uniform over the sphere
+            let z = 1. - rng.gen::<f32>() * 2.;
+            direction = (direction.truncate() * (1. - z * z).sqrt()).extend(z);
+        }
+
+        Boid::new(position, direction * speed)
+    }
+
+    /// one of the species of `params`, all equally likely
+    pub fn random_species(&mut self, params: &FlockParams) -> Species {
+        match params.species.len() {
+            // no draw, so that runs with a single species do not change
+            0 | 1 => Species(0),
+            count => Species(self.rng.gen_range(0..count)),
+        }
+    }
+
+    /// boids scattered over the arena with random headings, speeds and species
+    pub fn random_boids(&mut self, count: usize, params: &FlockParams) -> Vec<Boid> {
+        (0..count)
+            .map(|_| Boid {
+                species: self.random_species(params),
+                ..self.random_boid(params.min_speed, params.max_speed)
+            })
+            .collect()
+    }
+
+    /// boids in the disc (ball in a volume) of `radius` around `center`, with
+    /// random headings, speeds and species
+    pub fn random_boids_around(
+        &mut self,
+        count: usize,
+        params: &FlockParams,
+        center: Vec3,
+        radius: f32,
+    ) -> Vec<Boid> {
+        let depth = if self.arena.is_flat() { 0. } else { 1. };
+        (0..count)
+            .map(|_| {
+                let boid = self.random_boid(params.min_speed, params.max_speed);
+                let offset = loop {
+                    let rng = &mut self.rng;
+                    let unit = Vec3::new(rng.gen(), rng.gen(), rng.gen::<f32>() * depth);
+                    let offset = unit * 2. - Vec3::new(1., 1., depth);
+                    if offset.length_squared() <= 1. {
+                        break offset;
+                    }
+                };
+                Boid {
+                    species: self.random_species(params),
+                    ..Boid::new(center + offset * radius, boid.velocity)
+                }
+            })
+            .collect()
+    }
+
+    /// predators scattered over the arena with random headings
+    pub fn random_predators(&mut self, count: usize, params: &FlockParams) -> Vec<Predator> {
+        (0..count)
+            .map(|_| Predator {
+                body: self.random_boid(params.predator_speed, params.predator_speed),
+            })
+            .collect()
+    }
+
+    /// add boids scattered over the arena with random headings and speeds
+    pub fn add_random_boids(&mut self, count: usize, params: &FlockParams) {
+        let boids = self.random_boids(count, params);
+        self.boids.extend(boids);
+    }
+
+    /// add predators scattered over the arena with random headings
+    pub fn add_random_predators(&mut self, count: usize, params: &FlockParams) {
+        let predators = self.random_predators(count, params);
+        self.predators.extend(predators);
+    }
+
+    /// advance the simulation by `dt` seconds
+    pub fn step(&mut self, params: &FlockParams, behaviors: &SteeringBehaviors, dt: f32) {
+        for boid in &mut self.boids {
+            boid.save_previous();
+        }
+        for predator in &mut self.predators {
+            predator.body.save_previous();
+        }
+        let wrap = params.boundary == BoundaryMode::Wrap;
+        self.grid
+            .rebuild(&self.boids, params.neighbor_distance, &self.arena, wrap);
+        self.update_velocities(params, behaviors, dt);
+        for predator in &mut self.predators {
+            predator.chase(&self.boids, params, &self.arena, dt);
+        }
+        self.move_boids(params, dt);
+    }
+
+    /// accelerate the boids with respect to their neighbors
+    fn update_velocities(&mut self, params: &FlockParams, behaviors: &SteeringBehaviors, dt: f32) {
+        let surroundings = self.surroundings(params);
+        let steer = |scratch: &mut NeighborScratch, boid: &Boid| {
+            self.grid.neighbors_into(boid, &self.boids, params, scratch);
+            let neighbors = &scratch.neighbors;
+            let force = behaviors.force(boid, neighbors, &surroundings);
+            (force, neighbors.len())
+        };
+        // every boid only reads the others, so they can be steered in any order
+        let accelerations: Vec<(Vec3, usize)> = if self.parallel {
+            // outside of an app, e.g. headless, nobody has set up the pool yet
+            let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+            self.boids
+                .par_splat_map(pool, None, |chunk| {
+                    let mut scratch = NeighborScratch::default();
+                    chunk
+                        .iter()
+                        .map(|boid| steer(&mut scratch, boid))
+                        .collect::<Vec<_>>()
+                })
+                .into_iter()
+                .flatten()
+                .collect()
+        } else {
+            let mut scratch = NeighborScratch::default();
+            self.boids
+                .iter()
+                .map(|boid| steer(&mut scratch, boid))
+                .collect()
+        };
+
+        self.neighbor_counts.clear();
+        for (boid, (acceleration, count)) in self.boids.iter_mut().zip(accelerations) {
+            self.neighbor_counts.push(count);
+            let species = params.species_params(boid.species);
+            let velocity = boid.velocity + acceleration * species.agility * dt;
+            let speed = velocity.length().clamp(
+                params.min_speed * species.speed,
+                params.max_speed * species.speed,
+            );
+            boid.velocity = velocity.try_normalize().unwrap_or(BASE_DIRECTION) * speed;
+        }
+    }
+
+    /// a label for every boid, in the order of `boids`, shared by the boids
+    /// connected through neighbors; the label is the index of the first boid
+    /// of the cluster, so it only changes when clusters merge or split
+    pub fn clusters(&mut self, params: &FlockParams) -> Vec<usize> {
+        let wrap = params.boundary == BoundaryMode::Wrap;
+        self.grid
+            .rebuild(&self.boids, params.neighbor_distance, &self.arena, wrap);
+        // union-find, where the root of a set is its smallest index
+        let mut parents: Vec<usize> = (0..self.boids.len()).collect();
+        fn root(parents: &mut [usize], mut i: usize) -> usize {
+            while parents[i] != i {
+                parents[i] = parents[parents[i]];
+                i = parents[i];
+            }
+            i
+        }
+        for (i, boid) in self.boids.iter().enumerate() {
+            for j in self.grid.neighbor_indices(boid, &self.boids, params) {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[a.max(b)] = a.min(b);
+            }
+        }
+        (0..parents.len()).map(|i| root(&mut parents, i)).collect()
+    }
+
+    /// what the steering behaviors see of the flock besides the boids
+    pub fn surroundings<'a>(&'a self, params: &'a FlockParams) -> Surroundings<'a> {
+        Surroundings {
+            params,
+            arena: &self.arena,
+            obstacles: &self.obstacles,
+            predators: &self.predators,
+            lure: self.lure,
+        }
+    }
+
+    /// update the position of the boids and the predators
+    fn move_boids(&mut self, params: &FlockParams, dt: f32) {
+        let bodies = self.predators.iter_mut().map(|p| &mut p.body);
+        for boid in self.boids.iter_mut().chain(bodies) {
+            boid.advance(dt);
+            self.arena.confine(params.boundary, boid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Dimensions;
+
+    #[test]
+    fn step_keeps_boids_in_the_arena() {
+        let mut flock = Flock::with_seed(0);
+        let params = FlockParams::default();
+        flock.add_random_boids(500, &params);
+        for _ in 0..600 {
+            flock.step(&params, &SteeringBehaviors::default(), 1. / 60.);
+        }
+        assert_eq!(flock.boids.len(), 500);
+        for boid in &flock.boids {
+            assert!(boid.position.is_finite() && boid.velocity.is_finite());
+            assert!(boid.position.x.abs() <= 500. && boid.position.y.abs() <= 250.);
+            let speed = boid.velocity.length();
+            assert!(speed >= params.min_speed - 1e-3 && speed <= params.max_speed + 1e-3);
+        }
+    }
+
+    #[test]
+    fn step_keeps_boids_in_a_volume() {
+        let mut flock = Flock::with_seed(0);
+        flock.arena = Dimensions::Three.default_arena();
+        let params = FlockParams::default();
+        flock.add_random_boids(500, &params);
+        for _ in 0..600 {
+            flock.step(&params, &SteeringBehaviors::default(), 1. / 60.);
+        }
+        let half_size = flock.arena.half_size;
+        for boid in &flock.boids {
+            assert!(boid.position.abs().cmple(half_size).all());
+        }
+        assert!(flock.boids.iter().any(|b| b.velocity.z.abs() > 1.));
+    }
+
+    #[test]
+    fn the_view_is_a_cone_around_the_heading() {
+        let params = FlockParams::default();
+        let me = Boid::new(Vec3::ZERO, Vec3::Y);
+        // straight behind, the view angle leaves a blind cone of about 20 degrees
+        for i in 0..8 {
+            let around = Quat::from_rotation_y(i as f32 * 0.8) * Vec3::X;
+            assert!(sees(&me, Vec3::NEG_Y * 10. + around * 4., &params));
+            assert!(!sees(&me, Vec3::NEG_Y * 10. + around * 3., &params));
+        }
+    }
+
+    fn run(seed: u64) -> Vec<Boid> {
+        let mut flock = Flock::with_seed(seed);
+        let params = FlockParams::default();
+        flock.add_random_boids(300, &params);
+        for _ in 0..1000 {
+            flock.step(&params, &SteeringBehaviors::default(), 1. / 60.);
+        }
+        flock.boids
+    }
+
+    fn bits(boids: &[Boid]) -> Vec<[u32; 6]> {
+        boids
+            .iter()
+            .map(|b| {
+                let [x, y, z] = b.position.to_array();
+                let [u, v, w] = b.velocity.to_array();
+                [x, y, z, u, v, w].map(f32::to_bits)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parallel_and_serial_steps_agree() {
+        let params = FlockParams::default();
+        let behaviors = SteeringBehaviors::default();
+        let mut flocks = [true, false].map(|parallel| {
+            let mut flock = Flock::with_seed(5);
+            flock.parallel = parallel;
+            flock.add_random_boids(1000, &params);
+            flock
+        });
+        for _ in 0..100 {
+            for flock in &mut flocks {
+                flock.step(&params, &behaviors, 1. / 60.);
+            }
+        }
+        assert_eq!(bits(&flocks[0].boids), bits(&flocks[1].boids));
+    }
+
+    #[test]
+    fn step_keeps_the_previous_state_for_interpolation() {
+        let mut flock = Flock::with_seed(0);
+        flock.add_random_boids(10, &FlockParams::default());
+        let before = flock.boids.clone();
+        flock.step(
+            &FlockParams::default(),
+            &SteeringBehaviors::default(),
+            1. / 60.,
+        );
+        for (old, new) in before.iter().zip(&flock.boids) {
+            assert_eq!(new.previous_position, old.position);
+            assert_eq!(new.interpolated(0.).0, old.position);
+            assert!(new.interpolated(1.).0.abs_diff_eq(new.position, 1e-4));
+        }
+    }
+
+    #[test]
+    fn clusters_are_the_groups_of_connected_neighbors() {
+        let params = FlockParams::default();
+        let mut flock = Flock::with_seed(0);
+        // a row of boids seeing the ones beside them, and a lone one far away
+        flock.boids = [0., 40., 80., 120.]
+            .map(|x| Boid::new(Vec3::new(x - 300., 0., 0.), Vec3::Y))
+            .to_vec();
+        flock
+            .boids
+            .push(Boid::new(Vec3::new(300., 0., 0.), Vec3::Y));
+        assert_eq!(flock.clusters(&params), vec![0, 0, 0, 0, 4]);
+
+        flock.step(&params, &SteeringBehaviors::default(), 1. / 60.);
+        assert_eq!(flock.neighbor_counts, vec![1, 2, 2, 1, 0]);
+    }
+
+    #[test]
+    fn same_seed_gives_identical_runs() {
+        assert_eq!(bits(&run(42)), bits(&run(42)));
+        assert_ne!(bits(&run(42)), bits(&run(43)));
+    }
+}
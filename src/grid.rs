@@ -0,0 +1,357 @@
+use bevy::math::{IVec3, Vec3};
+use bevy::utils::HashMap;
+
+use crate::boundary::{Arena, BoundaryMode};
+use crate::flock::{ignores, sees, Boid};
+use crate::params::FlockParams;
+
+/// uniform grid bucketing the boids by position, rebuilt every tick
+///
+/// The cells are at least as large as the neighbor distance, so every neighbor
+/// of a boid lies in the cell of the boid or in one of the cells around it.
+/// In a wrapping arena the cells tile the arena and the ones on opposite edges
+/// are adjacent.
+#[derive(Default)]
+pub struct SpatialGrid {
+    cell_size: Vec3,
+    /// whether all the boids are in the plane z = 0, so that there is a single layer of cells
+    flat: bool,
+    /// the arena and the number of cells along each of its axes, when wrapping
+    wrap: Option<(Arena, IVec3)>,
+    cells: HashMap<IVec3, Vec<usize>>,
+}
+
+/// buffers of `SpatialGrid::neighbors_into`
+#[derive(Default)]
+pub struct NeighborScratch {
+    candidates: Vec<usize>,
+    /// the neighbors found by the last search
+    pub neighbors: Vec<Boid>,
+}
+
+impl SpatialGrid {
+    fn cell_of(&self, position: Vec3) -> IVec3 {
+        match self.wrap {
+            None => (position / self.cell_size).floor().as_ivec3(),
+            Some((arena, counts)) => ((position + arena.half_size) / self.cell_size)
+                .floor()
+                .as_ivec3()
+                .clamp(IVec3::ZERO, counts - IVec3::ONE),
+        }
+    }
+
+    /// put every boid into the cell containing its position, with cells tiling
+    /// the arena if the boids see across its edges
+    pub fn rebuild(&mut self, boids: &[Boid], neighbor_distance: f32, arena: &Arena, wrap: bool) {
+        let flat = arena.is_flat();
+        let (cell_size, wrap) = if wrap {
+            let size = arena.half_size * 2.;
+            let counts = (size / neighbor_distance).floor().max(Vec3::ONE);
+            let mut cell_size = size / counts;
+            if flat {
+                cell_size.z = neighbor_distance;
+            }
+            (cell_size, Some((*arena, counts.as_ivec3())))
+        } else {
+            (Vec3::splat(neighbor_distance), None)
+        };
+        if cell_size != self.cell_size || flat != self.flat || wrap != self.wrap {
+            self.cells.clear();
+            self.cell_size = cell_size;
+            self.flat = flat;
+            self.wrap = wrap;
+        }
+        // keep the allocations of the cells around between ticks
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        for (i, boid) in boids.iter().enumerate() {
+            let cell = self.cell_of(boid.position);
+            self.cells.entry(cell).or_default().push(i);
+        }
+        self.cells.retain(|_, cell| !cell.is_empty());
+    }
+
+    /// indices of the boids that may be neighbors of a boid at the given position,
+    /// in ascending order so that the result does not depend on the grid layout
+    pub fn candidates(&self, position: Vec3) -> Vec<usize> {
+        let mut candidates = Vec::new();
+        self.candidates_into(position, &mut candidates);
+        candidates
+    }
+
+    /// like `candidates`, reusing the allocation of `candidates`
+    pub fn candidates_into(&self, position: Vec3, candidates: &mut Vec<usize>) {
+        let center = self.cell_of(position);
+        let layers = if self.flat { 0 } else { 1 };
+        let mut cells = [IVec3::ZERO; 27];
+        let mut count = 0;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -layers..=layers {
+                    let cell = center + IVec3::new(dx, dy, dz);
+                    let cell = match self.wrap {
+                        None => cell,
+                        Some((_, counts)) => cell.rem_euclid(counts),
+                    };
+                    // a narrow wrapping arena has fewer than three cells along an axis
+                    if !cells[..count].contains(&cell) {
+                        cells[count] = cell;
+                        count += 1;
+                    }
+                }
+            }
+        }
+
+        candidates.clear();
+        for cell in &cells[..count] {
+            if let Some(cell) = self.cells.get(cell) {
+                candidates.extend_from_slice(cell);
+            }
+        }
+        candidates.sort_unstable();
+    }
+
+    /// shortest vector between two positions, across the edges when wrapping
+    fn offset(&self, from: Vec3, to: Vec3) -> Vec3 {
+        match self.wrap {
+            None => to - from,
+            Some((arena, _)) => arena.offset(BoundaryMode::Wrap, from, to),
+        }
+    }
+
+    /// indices of the neighbors of the given boid according to `sees`
+    pub fn neighbor_indices(&self, me: &Boid, boids: &[Boid], params: &FlockParams) -> Vec<usize> {
+        let mut candidates = self.candidates(me.position);
+        candidates.retain(|&i| {
+            !ignores(me, &boids[i], params)
+                && sees(me, self.offset(me.position, boids[i].position), params)
+        });
+        candidates
+    }
+
+    /// neighbors of the given boid according to `sees`; when wrapping, they are
+    /// moved next to the boid if they are seen across an edge
+    pub fn neighbors(&self, me: &Boid, boids: &[Boid], params: &FlockParams) -> Vec<Boid> {
+        let mut scratch = NeighborScratch::default();
+        self.neighbors_into(me, boids, params, &mut scratch);
+        scratch.neighbors
+    }
+
+    /// like `neighbors`, into `scratch.neighbors` and reusing its allocations,
+    /// so that a thread searching for many boids allocates only once
+    pub fn neighbors_into(
+        &self,
+        me: &Boid,
+        boids: &[Boid],
+        params: &FlockParams,
+        scratch: &mut NeighborScratch,
+    ) {
+        self.candidates_into(me.position, &mut scratch.candidates);
+        scratch.neighbors.clear();
+        for &i in &scratch.candidates {
+            let offset = self.offset(me.position, boids[i].position);
+            if !ignores(me, &boids[i], params) && sees(me, offset, params) {
+                scratch.neighbors.push(Boid {
+                    position: me.position + offset,
+                    ..boids[i]
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Dimensions;
+    use crate::flock::{is_neighbor, neighbors_among};
+    use crate::species::{Relation, Species};
+    use bevy::math::Vec2;
+    use bevy::utils::default;
+    use rand::prelude::*;
+
+    fn random_boids(count: usize, seed: u64) -> Vec<Boid> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count)
+            .map(|_| {
+                Boid::new(
+                    Vec3::new(
+                        500. - rng.gen::<f32>() * 1000.,
+                        250. - rng.gen::<f32>() * 500.,
+                        0.,
+                    ),
+                    Vec2::from_angle(rng.gen::<f32>() * std::f32::consts::TAU).extend(0.) * 100.,
+                )
+            })
+            .collect()
+    }
+
+    fn random_boids_3d(count: usize, seed: u64, arena: &Arena) -> Vec<Boid> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count)
+            .map(|_| {
+                let position = Vec3::new(rng.gen(), rng.gen(), rng.gen()) * 2. - Vec3::ONE;
+                let velocity = Vec3::new(rng.gen(), rng.gen(), rng.gen()) - Vec3::splat(0.5);
+                Boid::new(position * arena.half_size, velocity.normalize() * 100.)
+            })
+            .collect()
+    }
+
+    fn brute_force(
+        me: &Boid,
+        boids: &[Boid],
+        params: &FlockParams,
+        arena: &Arena,
+        wrap: bool,
+    ) -> Vec<usize> {
+        (0..boids.len())
+            .filter(|&i| {
+                if wrap {
+                    let offset = arena.offset(BoundaryMode::Wrap, me.position, boids[i].position);
+                    sees(me, offset, params)
+                } else {
+                    is_neighbor(me, &boids[i], params)
+                }
+            })
+            .collect()
+    }
+
+    fn assert_matches_brute_force(boids: &[Boid], params: &FlockParams, arena: &Arena, wrap: bool) {
+        let mut grid = SpatialGrid::default();
+        grid.rebuild(boids, params.neighbor_distance, arena, wrap);
+        for boid in boids {
+            assert_eq!(
+                grid.neighbor_indices(boid, boids, params),
+                brute_force(boid, boids, params, arena, wrap)
+            );
+        }
+    }
+
+    #[test]
+    fn grid_matches_brute_force() {
+        let boids = random_boids(2000, 1);
+        let arena = Arena::default();
+        assert_matches_brute_force(&boids, &FlockParams::default(), &arena, false);
+        let params = FlockParams {
+            neighbor_distance: 17.,
+            ..default()
+        };
+        assert_matches_brute_force(&boids, &params, &arena, false);
+    }
+
+    #[test]
+    fn grid_matches_brute_force_in_a_volume() {
+        let arena = Dimensions::Three.default_arena();
+        let boids = random_boids_3d(2000, 4, &arena);
+        let params = FlockParams::default();
+        assert_matches_brute_force(&boids, &params, &arena, false);
+        assert_matches_brute_force(&boids, &params, &arena, true);
+    }
+
+    #[test]
+    fn wrapping_grid_matches_brute_force() {
+        let boids = random_boids(2000, 3);
+        let arena = Arena::default();
+        assert_matches_brute_force(&boids, &FlockParams::default(), &arena, true);
+        // cells that do not divide the arena evenly
+        let params = FlockParams {
+            neighbor_distance: 33.,
+            ..default()
+        };
+        assert_matches_brute_force(&boids, &params, &arena, true);
+        // an arena so narrow that both neighbors of a cell are the same cell
+        let narrow = Arena::new(Vec3::new(1000., 120., 0.));
+        let boids: Vec<Boid> = boids
+            .into_iter()
+            .map(|mut b| {
+                b.position.y *= 60. / 250.;
+                b
+            })
+            .collect();
+        assert_matches_brute_force(&boids, &FlockParams::default(), &narrow, true);
+    }
+
+    #[test]
+    fn neighbors_across_the_edge_are_moved_next_to_the_boid() {
+        let params = FlockParams::default();
+        let boids = [
+            Boid::new(Vec3::new(495., 0., 0.), Vec3::X),
+            Boid::new(Vec3::new(-495., 0., 0.), Vec3::X),
+        ];
+        let mut grid = SpatialGrid::default();
+        grid.rebuild(&boids, params.neighbor_distance, &Arena::default(), true);
+        let neighbors = grid.neighbors(&boids[0], &boids, &params);
+        assert_eq!(neighbors.len(), 1);
+        assert!(neighbors[0]
+            .position
+            .abs_diff_eq(Vec3::new(505., 0., 0.), 1e-3));
+        let params = FlockParams {
+            boundary: BoundaryMode::Wrap,
+            ..params
+        };
+        assert_eq!(
+            neighbors_among(&boids[0], &boids, &Arena::default(), &params),
+            neighbors
+        );
+    }
+
+    #[test]
+    fn ignored_species_are_not_neighbors() {
+        // species 0 ignores species 1, which flocks with everyone
+        let params = FlockParams {
+            species: vec![default(); 2],
+            relations: vec![vec![Relation::Flock, Relation::Ignore]],
+            ..default()
+        };
+        let boids = [
+            Boid::new(Vec3::ZERO, Vec3::X),
+            Boid {
+                species: Species(1),
+                ..Boid::new(Vec3::new(10., 0., 0.), Vec3::NEG_X)
+            },
+            Boid::new(Vec3::new(20., 0., 0.), Vec3::X),
+        ];
+        let mut grid = SpatialGrid::default();
+        grid.rebuild(&boids, params.neighbor_distance, &Arena::default(), false);
+        assert_eq!(grid.neighbor_indices(&boids[0], &boids, &params), vec![2]);
+        assert_eq!(grid.neighbors(&boids[0], &boids, &params), vec![boids[2]]);
+        assert_eq!(grid.neighbor_indices(&boids[1], &boids, &params), vec![0]);
+    }
+
+    #[test]
+    fn grid_handles_cell_borders() {
+        // boids sitting exactly on cell borders and on both sides of the origin
+        let params = FlockParams::default();
+        let cell = params.neighbor_distance;
+        let boids: Vec<Boid> = [
+            -cell,
+            -cell / 2.,
+            -0.01,
+            0.,
+            0.01,
+            cell / 2.,
+            cell,
+            2. * cell,
+        ]
+        .iter()
+        .flat_map(|&x| {
+            [-cell, 0., cell]
+                .map(|y| Boid::new(Vec3::new(x, y, 0.), Vec2::from_angle(x + y).extend(0.)))
+        })
+        .collect();
+        assert_matches_brute_force(&boids, &params, &Arena::default(), false);
+    }
+
+    #[test]
+    fn rebuild_forgets_old_positions() {
+        let mut boids = random_boids(100, 2);
+        let mut grid = SpatialGrid::default();
+        grid.rebuild(&boids, 50., &Arena::default(), false);
+        for boid in &mut boids {
+            boid.position += Vec3::new(1000., 1000., 0.);
+        }
+        grid.rebuild(&boids, 50., &Arena::default(), false);
+        assert!(grid.candidates(Vec3::ZERO).is_empty());
+    }
+}
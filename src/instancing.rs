@@ -0,0 +1,299 @@
+use bevy::asset::load_internal_asset;
+use bevy::core_pipeline::core_2d::Transparent2d;
+use bevy::ecs::query::QueryItem;
+use bevy::ecs::system::lifetimeless::SRes;
+use bevy::ecs::system::SystemParamItem;
+use bevy::prelude::*;
+use bevy::render::extract_component::{ExtractComponent, ExtractComponentPlugin};
+use bevy::render::render_phase::{
+    AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult, RenderPhase,
+    SetItemPipeline, TrackedRenderPass,
+};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::texture::BevyDefault;
+use bevy::render::view::{ExtractedView, ViewTarget};
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::sprite::{Mesh2dPipeline, Mesh2dPipelineKey, SetMesh2dViewBindGroup};
+use bevy::utils::FloatOrd;
+use bytemuck::{Pod, Zeroable};
+
+use crate::coloring::{boid_color, ColorMode, Neighborhood};
+use crate::flock::Boid;
+use crate::params::FlockParams;
+
+const SHADER: Handle<Shader> = Handle::weak_from_u128(0x3d1c_9f4e_8b27_4a61_a0e5_6c2f_91b8_d347);
+
+/// the boid shape, pointing along `BASE_DIRECTION`, shared by all boids
+const TRIANGLE: [Vec2; 3] = [
+    Vec2::new(0., 5.),
+    Vec2::new(-4.33, -2.5),
+    Vec2::new(4.33, -2.5),
+];
+
+/// what the shader needs to know about one boid
+#[derive(Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct BoidInstance {
+    position: Vec3,
+    /// cosine and sine of the rotation of the triangle
+    rotation: Vec2,
+    /// linear RGBA
+    color: [f32; 4],
+}
+
+/// all boids of the frame, drawn with a single instanced draw call; a
+/// component of one entity, so that it can be extracted like any other
+#[derive(Component, Clone, Default)]
+struct BoidInstances(Vec<BoidInstance>);
+
+impl ExtractComponent for BoidInstances {
+    type Query = &'static Self;
+    type Filter = ();
+    type Out = Self;
+
+    fn extract_component(item: QueryItem<'_, Self::Query>) -> Option<Self> {
+        Some(item.clone())
+    }
+}
+
+fn add_instances(mut commands: Commands) {
+    commands.spawn(BoidInstances::default());
+}
+
+/// collect the boids where `draw_boids` put them for this frame, colored
+/// according to the `ColorMode`
+fn gather_instances(
+    boids: Query<(&Transform, &Boid, Option<&Neighborhood>)>,
+    mut instances: Query<&mut BoidInstances>,
+    params: Res<FlockParams>,
+    mode: Res<ColorMode>,
+) {
+    let Ok(mut instances) = instances.get_single_mut() else {
+        return;
+    };
+    instances.0.clear();
+    instances
+        .0
+        .extend(boids.iter().map(|(transform, boid, neighborhood)| {
+            // the rotation taking the Y axis to `heading` has cosine `heading.y`
+            // and sine `-heading.x`
+            let heading = (transform.rotation * Vec3::Y)
+                .truncate()
+                .normalize_or_zero();
+            BoidInstance {
+                position: transform.translation,
+                rotation: Vec2::new(heading.y, -heading.x),
+                // boids spawned this frame get their neighborhood later
+                color: boid_color(*mode, boid, neighborhood.unwrap_or(&default()), &params)
+                    .as_linear_rgba_f32(),
+            }
+        }));
+}
+
+/// the pipeline drawing the boid instances, and the triangle they share
+#[derive(Resource)]
+struct BoidPipeline {
+    view_layout: BindGroupLayout,
+    triangle: Buffer,
+}
+
+impl FromWorld for BoidPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let view_layout = world.resource::<Mesh2dPipeline>().view_layout.clone();
+        let triangle =
+            world
+                .resource::<RenderDevice>()
+                .create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("boid_triangle"),
+                    contents: bytemuck::cast_slice(TRIANGLE.as_slice()),
+                    usage: BufferUsages::VERTEX,
+                });
+        Self {
+            view_layout,
+            triangle,
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for BoidPipeline {
+    type Key = Mesh2dPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let corners = VertexBufferLayout::from_vertex_formats(
+            VertexStepMode::Vertex,
+            [VertexFormat::Float32x2],
+        );
+        let instances = VertexBufferLayout {
+            array_stride: std::mem::size_of::<BoidInstance>() as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: vec![
+                VertexAttribute {
+                    format: VertexFormat::Float32x3,
+                    offset: 0,
+                    shader_location: 1,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x2,
+                    offset: 12,
+                    shader_location: 2,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 20,
+                    shader_location: 3,
+                },
+            ],
+        };
+        let format = if key.contains(Mesh2dPipelineKey::HDR) {
+            ViewTarget::TEXTURE_FORMAT_HDR
+        } else {
+            TextureFormat::bevy_default()
+        };
+        RenderPipelineDescriptor {
+            label: Some("boid_pipeline".into()),
+            layout: vec![self.view_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            vertex: VertexState {
+                shader: SHADER,
+                shader_defs: Vec::new(),
+                entry_point: "vertex".into(),
+                buffers: vec![corners, instances],
+            },
+            fragment: Some(FragmentState {
+                shader: SHADER,
+                shader_defs: Vec::new(),
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: key.msaa_samples(),
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        }
+    }
+}
+
+/// the instances of all boids on the GPU
+#[derive(Resource, Deref, DerefMut)]
+struct InstanceBuffer(BufferVec<BoidInstance>);
+
+/// draw the boids in every 2D view, below the sprites of the predators
+fn queue_boids(
+    draw_functions: Res<DrawFunctions<Transparent2d>>,
+    pipeline: Res<BoidPipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<BoidPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    msaa: Res<Msaa>,
+    instances: Query<(Entity, &BoidInstances)>,
+    mut views: Query<(&ExtractedView, &mut RenderPhase<Transparent2d>)>,
+) {
+    let draw_boids = draw_functions.read().id::<DrawBoids>();
+    for (view, mut phase) in &mut views {
+        // the 2D camera does no tonemapping, so the shader does not either
+        let key = Mesh2dPipelineKey::from_msaa_samples(msaa.samples())
+            | Mesh2dPipelineKey::from_hdr(view.hdr)
+            | Mesh2dPipelineKey::from_primitive_topology(PrimitiveTopology::TriangleList);
+        let pipeline = pipelines.specialize(&pipeline_cache, &pipeline, key);
+        for (entity, instances) in &instances {
+            if instances.0.is_empty() {
+                continue;
+            }
+            phase.add(Transparent2d {
+                sort_key: FloatOrd(0.),
+                entity,
+                pipeline,
+                draw_function: draw_boids,
+                batch_range: 0..1,
+                dynamic_offset: None,
+            });
+        }
+    }
+}
+
+fn prepare_instances(
+    instances: Query<&BoidInstances>,
+    mut buffer: ResMut<InstanceBuffer>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    buffer.clear();
+    for instances in &instances {
+        buffer.extend(instances.0.iter().copied());
+    }
+    buffer.write_buffer(&device, &queue);
+}
+
+type DrawBoids = (
+    SetItemPipeline,
+    SetMesh2dViewBindGroup<0>,
+    DrawBoidInstances,
+);
+
+struct DrawBoidInstances;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawBoidInstances {
+    type Param = (SRes<BoidPipeline>, SRes<InstanceBuffer>);
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = ();
+
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        _entity: (),
+        (pipeline, instances): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let instances = instances.into_inner();
+        let Some(buffer) = instances.buffer() else {
+            return RenderCommandResult::Failure;
+        };
+        pass.set_vertex_buffer(0, pipeline.into_inner().triangle.slice(..));
+        pass.set_vertex_buffer(1, buffer.slice(..));
+        pass.draw(0..TRIANGLE.len() as u32, 0..instances.len() as u32);
+        RenderCommandResult::Success
+    }
+}
+
+/// draw all boids of the 2D view from one instance buffer, with one draw call
+pub struct InstancingPlugin;
+
+impl Plugin for InstancingPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, SHADER, "instancing.wgsl", Shader::from_wgsl);
+        app.add_plugins(ExtractComponentPlugin::<BoidInstances>::default())
+            .add_systems(Startup, add_instances)
+            .add_systems(PostUpdate, gather_instances);
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_command::<Transparent2d, DrawBoids>()
+            .init_resource::<SpecializedRenderPipelines<BoidPipeline>>()
+            .insert_resource(InstanceBuffer(BufferVec::new(BufferUsages::VERTEX)))
+            .add_systems(
+                Render,
+                (
+                    queue_boids.in_set(RenderSet::QueueMeshes),
+                    prepare_instances.in_set(RenderSet::PrepareResources),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<BoidPipeline>();
+        }
+    }
+}
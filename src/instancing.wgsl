@@ -0,0 +1,35 @@
+#import bevy_sprite::mesh2d_view_bindings::view
+
+struct Vertex {
+    // corner of the boid triangle, pointing up
+    @location(0) corner: vec2<f32>,
+    // the other attributes are per instance
+    @location(1) position: vec3<f32>,
+    // cosine and sine of the rotation
+    @location(2) rotation: vec2<f32>,
+    @location(3) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let c = vertex.rotation.x;
+    let s = vertex.rotation.y;
+    let corner = vec2<f32>(
+        c * vertex.corner.x - s * vertex.corner.y,
+        s * vertex.corner.x + c * vertex.corner.y,
+    );
+    var out: VertexOutput;
+    out.clip_position = view.view_proj * vec4<f32>(vertex.position + vec3<f32>(corner, 0.0), 1.0);
+    out.color = vertex.color;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,35 @@
+pub mod boundary;
+pub mod camera;
+pub mod cli;
+pub mod clock;
+pub mod coloring;
+pub mod config;
+pub mod flock;
+pub mod grid;
+pub mod instancing;
+pub mod lure;
+pub mod obstacle;
+pub mod overlay;
+pub mod panel;
+pub mod params;
+pub mod plugin;
+pub mod pointer;
+pub mod predator;
+pub mod spawn;
+pub mod species;
+pub mod steering;
+pub mod trails;
+pub mod view3d;
+
+pub use boundary::{Arena, BoundaryMode, Dimensions};
+pub use coloring::ColorMode;
+pub use flock::{Boid, Flock};
+pub use lure::Lure;
+pub use obstacle::Obstacle;
+pub use params::FlockParams;
+pub use plugin::BoidsPlugin;
+pub use predator::Predator;
+pub use spawn::{DespawnBoids, SpawnBoids};
+pub use species::{Relation, Species, SpeciesParams};
+pub use steering::{AddSteeringBehavior, SteeringBehavior, SteeringBehaviors};
+pub use trails::TrailSettings;
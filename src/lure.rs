@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+use crate::flock::Boid;
+use crate::params::FlockParams;
+use crate::plugin::Boids;
+use crate::pointer::Pointer;
+use crate::steering::{steer_towards, SteeringBehavior, Surroundings};
+
+/// a point the boids within `FlockParams::lure_radius` are drawn to or driven away from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lure {
+    Attract(Vec3),
+    Repel(Vec3),
+}
+
+/// steer towards an attracting lure, or away from a repelling one the stronger
+/// the closer it is
+pub struct FollowLure;
+
+impl SteeringBehavior for FollowLure {
+    fn steer(&self, boid: &Boid, _neighbors: &[Boid], surroundings: &Surroundings) -> Vec3 {
+        let params = surroundings.params;
+        let Some(lure) = surroundings.lure else {
+            return Vec3::ZERO;
+        };
+        let (Lure::Attract(position) | Lure::Repel(position)) = lure;
+        let towards = surroundings
+            .arena
+            .offset(params.boundary, boid.position, position);
+        let distance = towards.length();
+        if distance >= params.lure_radius {
+            return Vec3::ZERO;
+        }
+        match lure {
+            Lure::Attract(_) => steer_towards(towards, boid.velocity, params),
+            Lure::Repel(_) => {
+                steer_towards(-towards, boid.velocity, params)
+                    * (1. - distance / params.lure_radius)
+            }
+        }
+    }
+
+    fn weight(&self, params: &FlockParams) -> f32 {
+        params.lure_weight
+    }
+}
+
+/// dragging with the left mouse button attracts the boids, holding the right
+/// one repels them; shift clicks are left to `SpawnPlugin`, ctrl clicks to
+/// `CameraPlugin`
+fn lure_with_mouse(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    pointer: Res<Pointer>,
+    mut boids: ResMut<Boids>,
+) {
+    let modified = keys.any_pressed([
+        KeyCode::ShiftLeft,
+        KeyCode::ShiftRight,
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+    ]);
+    let lure = match **pointer {
+        Some(cursor) if !modified && buttons.pressed(MouseButton::Left) => {
+            Some(Lure::Attract(cursor))
+        }
+        Some(cursor) if !modified && buttons.pressed(MouseButton::Right) => {
+            Some(Lure::Repel(cursor))
+        }
+        _ => None,
+    };
+    if boids.lure != lure {
+        boids.lure = lure;
+    }
+}
+
+/// circle around the mouse while it lures the boids
+fn draw_lure(boids: Res<Boids>, params: Res<FlockParams>, mut gizmos: Gizmos) {
+    let (position, color) = match boids.lure {
+        Some(Lure::Attract(position)) => (position, Color::LIME_GREEN),
+        Some(Lure::Repel(position)) => (position, Color::TOMATO),
+        None => return,
+    };
+    gizmos.circle(position, Vec3::Z, params.lure_radius, color);
+}
+
+pub struct LurePlugin;
+
+impl Plugin for LurePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (lure_with_mouse, draw_lure).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Arena;
+
+    #[test]
+    fn lures_attract_and_repel_within_their_radius() {
+        let params = FlockParams::default();
+        let arena = Arena::default();
+        let boid = Boid::new(Vec3::ZERO, Vec3::Y * params.max_speed);
+        let steer = |lure| {
+            let surroundings = Surroundings {
+                params: &params,
+                arena: &arena,
+                obstacles: &[],
+                predators: &[],
+                lure: Some(lure),
+            };
+            FollowLure.steer(&boid, &[], &surroundings)
+        };
+        let near = Vec3::new(50., 0., 0.);
+        assert!(steer(Lure::Attract(near)).x > 0.);
+        assert!(steer(Lure::Repel(near)).x < 0.);
+        let far = Vec3::new(params.lure_radius, 0., 0.);
+        assert_eq!(steer(Lure::Attract(far)), Vec3::ZERO);
+        assert_eq!(steer(Lure::Repel(far)), Vec3::ZERO);
+    }
+}
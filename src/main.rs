@@ -1,200 +1,93 @@
 use bevy::prelude::*;
-use bevy::sprite::MaterialMesh2dBundle;
-use rand::prelude::*;
-
-#[derive(Clone, Copy)]
-struct Boid {
-    position: Vec3,
-    rotation: Quat,
-}
-
-#[derive(Resource)]
-struct Boids(Vec<Boid>);
-
-#[derive(Component)]
-struct BoidRef(usize);
-
-fn add_camera(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
-}
-
-const BASE_DIRECTION: Vec3 = Vec3::new(0., 1., 0.);
-const VELOCITY: f32 = 100.;
-
-/// return a quaternion representing the rotation from one vector to another
-fn diff_as_quat(from: Vec3, to: Vec3) -> Quat {
-    let rotation_axis = from.cross(to);
-    let rotation_angle = from.angle_between(to);
-    let q = Quat::from_axis_angle(rotation_axis.normalize(), rotation_angle).normalize();
-    if q.is_nan() || q.is_near_identity() {
-        if rand::random() {
-            Quat::IDENTITY
-        } else {
-            Quat::from_rotation_z(std::f32::consts::PI)
-        }
-    } else {
-        q
-    }
-}
-
-/// initialize the scene with a bunch of boids
-fn add_boids(
-    mut boids: ResMut<Boids>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-) {
-    let mut rng = rand::thread_rng();
-    for i in 0..300 {
-        let pos_x = 500. - rng.gen::<f32>() * 1000.;
-        let pos_y = 250. - rng.gen::<f32>() * 500.;
-        let vel_x = 1. - rng.gen::<f32>() * 2.;
-        let vel_y = 1. - rng.gen::<f32>() * 2.;
-
-        let position = Vec3::new(pos_x, pos_y, 0.);
-        let rotation = diff_as_quat(BASE_DIRECTION, Vec3::new(vel_x, vel_y, 0.));
-
-        boids.0.push(Boid { position, rotation });
-
-        commands.spawn((
-            // boid reference
-            BoidRef(i),
-            // boid sprite
-            MaterialMesh2dBundle {
-                mesh: meshes.add(shape::RegularPolygon::new(5., 3).into()).into(),
-                material: materials.add(ColorMaterial::from(Color::TURQUOISE)),
-                transform: Transform::from_translation(position).with_rotation(rotation),
-                ..default()
-            },
-        ));
-        /*
-        // a mark for the center of the scene
-        commands.spawn(
-            MaterialMesh2dBundle {
-                mesh: meshes.add(shape::RegularPolygon::new(5., 8).into()).into(),
-                material: materials.add(ColorMaterial::from(Color::WHITE)),
-                transform: Transform::from_translation(Vec3::new(0.,0.,0.)),
-                ..default()
-            },
-        );
-        */
-    }
-}
-
-/// update the position of the boids
-fn move_boids(mut boids: ResMut<Boids>, time: Res<Time>) {
-    for boid in &mut boids.0 {
-        let velocity = boid.rotation.mul_vec3(BASE_DIRECTION * VELOCITY);
-        boid.position.x += velocity.x * time.delta_seconds();
-        boid.position.y += velocity.y * time.delta_seconds();
-
-        // bounce off the walls
-        if boid.position.x.abs() > 500. || boid.position.y.abs() > 250. {
-            boid.rotation = boid
-                .rotation
-                .mul_quat(Quat::from_rotation_z(std::f32::consts::PI));
-        }
-        if boid.position.y.abs() > 250. {
-            boid.position.y *= 0.9;
-        }
-        if boid.position.x.abs() > 500. {
-            boid.position.x *= 0.9;
-        }
-    }
-}
-/// update the position of the boid sprites
-fn draw_boids(boids: Res<Boids>, mut query: Query<(&BoidRef, &mut Transform), With<BoidRef>>) {
-    for (br, mut transform) in &mut query {
-        let boid = &boids.0[br.0];
-        transform.translation = boid.position;
-        transform.rotation = boid.rotation;
-    }
-}
-
-const NEIGHBOR_DISTANCE_SQUARED: f32 = 50.0 * 50.0;
-const NEIGHBOR_ANGLE: f32 = 2.79; // 160.0.to_radians();
-
-/// check if two boids are neighbors according to the distance and angle criteria
-fn is_neighbor(me: &Boid, other: &Boid) -> bool {
-    let direction = other.position - me.position;
-
-    // Check distance criterion
-    if direction.length_squared() >= NEIGHBOR_DISTANCE_SQUARED {
-        return false;
-    }
-
-    // Calculate the angle between boids in degrees
-    let angle = me
-        .rotation
-        .mul_vec3(BASE_DIRECTION)
-        .angle_between(direction);
-
-    // Check angle criterion
-    angle < NEIGHBOR_ANGLE
+use boids::cli::ParamOverrides;
+use boids::{BoidsPlugin, ColorMode, Dimensions, FlockParams, TrailSettings};
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(about = "boids flocking simulation")]
+struct Cli {
+    /// `.ron` or `.toml` file with the flock parameters, relative to the
+    /// assets folder; edits to it are applied while the app runs
+    #[arg(long, value_name = "PATH", default_value = "flock.ron")]
+    config: String,
+    /// seed of the simulation, runs with the same seed are identical
+    #[arg(long)]
+    seed: Option<u64>,
+    /// simulation ticks per second, independent of the frame rate
+    #[arg(long, default_value_t = 60., value_parser = positive::<f64>)]
+    tick_rate: f64,
+    /// number of predators hunting the flock
+    #[arg(long, default_value_t = 1)]
+    predators: usize,
+    /// fly in a plane or in a volume
+    #[arg(long, value_enum, default_value_t)]
+    dimensions: Dimensions,
+    /// fixed size of the arena, scaled to fit the window in 2D; without it the
+    /// flat arena covers the window and follows its size; the depth defaults
+    /// to the height
+    #[arg(
+        long,
+        num_args = 2..=3,
+        value_names = ["WIDTH", "HEIGHT", "DEPTH"],
+        value_parser = positive::<f32>
+    )]
+    world: Option<Vec<f32>>,
+    /// what the colors of the boids show at first, cycled with V
+    #[arg(long, value_enum, default_value_t)]
+    colors: ColorMode,
+    /// show trails behind the boids from the start, toggled with T
+    #[arg(long)]
+    trails: bool,
+    /// number of past positions in the trails, one per tick
+    #[arg(long, default_value_t = TrailSettings::default().length)]
+    trail_length: usize,
+    #[command(flatten)]
+    overrides: ParamOverrides,
 }
 
-/// update the direction of the boids with respect to their neighbors
-fn update_direction_of_boids(mut boids: ResMut<Boids>) {
-    let mut updates = Vec::new();
-    for boid in &boids.0 {
-        let mut neighbors = Vec::new();
-        for b in &boids.0 {
-            if is_neighbor(boid, b) {
-                neighbors.push(b);
-            }
-        }
-        if neighbors.is_empty() {
-            updates.push((Quat::IDENTITY, boid.rotation));
-            continue;
-        }
-        let vel = boid.rotation.mul_vec3(BASE_DIRECTION);
-        // calculate the center of the group
-        let avg_pos = neighbors.iter().map(|b| b.position).sum::<Vec3>() / neighbors.len() as f32;
-        // calculate the direction to the center of the group
-        let convergence = diff_as_quat(vel, avg_pos - boid.position);
-
-        // direction to avoid collision
-        let avoidance = neighbors
-            .iter()
-            .map(|b| {
-                // avoid boids that are too close
-                let length_squared = (boid.position - b.position).length_squared();
-                if length_squared < NEIGHBOR_DISTANCE_SQUARED / 4. {
-                    (boid.position - b.position) / length_squared.max(1.0)
-                } else {
-                    Vec3::ZERO
-                }
-            })
-            .sum::<Vec3>();
-        // calculate the average direction to avoid collision
-        let avoidance = diff_as_quat(vel, avoidance);
-
-        // average rotation of the neighbors
-        let avg_rot = neighbors.iter().map(|b| b.rotation).sum::<Quat>() / neighbors.len() as f32;
-
-        // first element is relative to the boid, second is absolute
-        updates.push(((convergence * 10. + avoidance * 11.).normalize(), avg_rot));
-    }
-
-    for (boid, (upd, avg_rot)) in boids.0.iter_mut().zip(updates) {
-        // first apply the relative rotation
-        boid.rotation *= upd / 100.;
-        // then align with the average rotation of the neighbors
-        boid.rotation = (boid.rotation * 0.95 + avg_rot * 0.05).normalize();
-    }
-}
-
-struct BoidsPlugin;
-
-impl Plugin for BoidsPlugin {
-    fn build(&self, app: &mut App) {
-        app.insert_resource(Boids(Vec::new()))
-            .add_systems(Startup, (add_camera, add_boids))
-            .add_systems(Update, (update_direction_of_boids, move_boids))
-            .add_systems(FixedUpdate, draw_boids);
+/// a finite number above 0
+fn positive<T>(text: &str) -> Result<T, String>
+where
+    T: std::str::FromStr + Into<f64> + Copy,
+    T::Err: std::fmt::Display,
+{
+    match text.parse::<T>() {
+        Ok(value) if value.into() > 0. && value.into().is_finite() => Ok(value),
+        Ok(_) => Err("must be a finite number above 0".to_owned()),
+        Err(e) => Err(e.to_string()),
     }
 }
 
 fn main() {
-    App::new().add_plugins((DefaultPlugins, BoidsPlugin)).run();
+    let cli = Cli::parse();
+    let params = cli
+        .overrides
+        .apply(FlockParams::default())
+        .unwrap_or_else(|e| {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        });
+
+    App::new()
+        .insert_resource(params)
+        .insert_resource(cli.overrides)
+        .insert_resource(cli.colors)
+        .insert_resource(TrailSettings {
+            enabled: cli.trails,
+            length: cli.trail_length,
+        })
+        .add_plugins((
+            DefaultPlugins,
+            BoidsPlugin {
+                config: cli.config,
+                seed: cli.seed,
+                tick_rate: cli.tick_rate,
+                predators: cli.predators,
+                dimensions: cli.dimensions,
+                world_size: cli
+                    .world
+                    .map(|size| Vec3::new(size[0], size[1], *size.get(2).unwrap_or(&size[1]))),
+            },
+        ))
+        .run();
 }
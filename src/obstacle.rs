@@ -0,0 +1,378 @@
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+
+use crate::flock::Boid;
+use crate::params::FlockParams;
+use crate::plugin::Boids;
+use crate::steering::{steer_towards, SteeringBehavior, Surroundings};
+
+const OBSTACLE_COLOR: Color = Color::GRAY;
+
+/// a static obstacle the boids fly around, in world coordinates
+#[derive(Component, Clone, Debug, PartialEq)]
+pub enum Obstacle {
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    /// axis-aligned box
+    Box {
+        min: Vec2,
+        max: Vec2,
+    },
+    /// open chain of segments, a wall without an inside
+    Polyline {
+        points: Vec<Vec2>,
+    },
+}
+
+/// where a ray hits an obstacle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// distance from the origin of the ray, 0 if the origin is inside the obstacle
+    pub distance: f32,
+    /// unit normal of the surface, facing the origin of the ray
+    pub normal: Vec2,
+}
+
+impl Obstacle {
+    /// first hit of the ray from `origin` along the unit vector `direction`
+    /// within `max_distance`
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<Hit> {
+        let hit = match self {
+            Obstacle::Circle { center, radius } => {
+                raycast_circle(origin, direction, *center, *radius)
+            }
+            Obstacle::Box { min, max } => raycast_box(origin, direction, *min, *max),
+            Obstacle::Polyline { points } => points
+                .windows(2)
+                .filter_map(|s| raycast_segment(origin, direction, s[0], s[1]))
+                .min_by(|a, b| a.distance.total_cmp(&b.distance)),
+        };
+        hit.filter(|hit| hit.distance <= max_distance)
+    }
+}
+
+fn raycast_circle(origin: Vec2, direction: Vec2, center: Vec2, radius: f32) -> Option<Hit> {
+    let m = origin - center;
+    let c = m.length_squared() - radius * radius;
+    if c <= 0. {
+        // inside, get out the shortest way
+        let normal = m.try_normalize().unwrap_or(-direction);
+        return Some(Hit {
+            distance: 0.,
+            normal,
+        });
+    }
+    let b = m.dot(direction);
+    let discriminant = b * b - c;
+    if b > 0. || discriminant < 0. {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    let normal = (m + direction * distance) / radius;
+    Some(Hit { distance, normal })
+}
+
+fn raycast_box(origin: Vec2, direction: Vec2, min: Vec2, max: Vec2) -> Option<Hit> {
+    if origin.cmpge(min).all() && origin.cmple(max).all() {
+        // inside, get out through the closest side
+        let exits = [
+            (origin.x - min.x, Vec2::NEG_X),
+            (max.x - origin.x, Vec2::X),
+            (origin.y - min.y, Vec2::NEG_Y),
+            (max.y - origin.y, Vec2::Y),
+        ];
+        let (_, normal) = exits
+            .into_iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap();
+        return Some(Hit {
+            distance: 0.,
+            normal,
+        });
+    }
+    // slab method, remembering the axis through which the ray enters
+    let mut enter = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = Vec2::ZERO;
+    for (axis, unit) in [(0, Vec2::X), (1, Vec2::Y)] {
+        if direction[axis] == 0. {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min[axis] - origin[axis]) / direction[axis];
+        let t2 = (max[axis] - origin[axis]) / direction[axis];
+        let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+        if near > enter {
+            enter = near;
+            normal = -unit * direction[axis].signum();
+        }
+        exit = exit.min(far);
+    }
+    (enter <= exit && enter >= 0.).then_some(Hit {
+        distance: enter,
+        normal,
+    })
+}
+
+fn raycast_segment(origin: Vec2, direction: Vec2, a: Vec2, b: Vec2) -> Option<Hit> {
+    let along = b - a;
+    let denominator = direction.perp_dot(along);
+    if denominator == 0. {
+        return None;
+    }
+    let to_a = a - origin;
+    let distance = to_a.perp_dot(along) / denominator;
+    let t = to_a.perp_dot(direction) / denominator;
+    if distance < 0. || !(0. ..=1.).contains(&t) {
+        return None;
+    }
+    let normal = along.perp().normalize();
+    let normal = if normal.dot(direction) > 0. {
+        -normal
+    } else {
+        normal
+    };
+    Some(Hit { distance, normal })
+}
+
+/// look ahead along the heading (and two whiskers to the sides) and swerve
+/// around the closest obstacle in the way, the closer the harder
+pub struct ObstacleAvoidance;
+
+/// angle between the heading and the side whiskers
+const WHISKER_ANGLE: f32 = 0.5;
+
+impl SteeringBehavior for ObstacleAvoidance {
+    fn steer(&self, boid: &Boid, _neighbors: &[Boid], surroundings: &Surroundings) -> Vec3 {
+        let params = surroundings.params;
+        let Some(heading) = boid.velocity.truncate().try_normalize() else {
+            return Vec3::ZERO;
+        };
+        let origin = boid.position.truncate();
+        let feelers = [
+            (heading, params.obstacle_look_ahead),
+            (
+                Vec2::from_angle(WHISKER_ANGLE).rotate(heading),
+                params.obstacle_look_ahead / 2.,
+            ),
+            (
+                Vec2::from_angle(-WHISKER_ANGLE).rotate(heading),
+                params.obstacle_look_ahead / 2.,
+            ),
+        ];
+        let closest = feelers
+            .iter()
+            .flat_map(|&(direction, length)| {
+                surroundings
+                    .obstacles
+                    .iter()
+                    .filter_map(move |o| o.raycast(origin, direction, length))
+                    .map(move |hit| (hit, length))
+            })
+            .min_by(|a, b| a.0.distance.total_cmp(&b.0.distance));
+        let Some((hit, length)) = closest else {
+            return Vec3::ZERO;
+        };
+
+        // slide along the surface, away from it
+        let along = heading.reject_from_normalized(hit.normal);
+        let along = along.try_normalize().unwrap_or(hit.normal.perp());
+        let desired = along + hit.normal;
+        let urgency = 1. - hit.distance / length;
+        steer_towards(desired.extend(0.), boid.velocity, params) * urgency
+    }
+
+    fn weight(&self, params: &FlockParams) -> f32 {
+        params.obstacle_weight
+    }
+}
+
+/// copy the obstacle entities into the flock whenever they change
+fn sync_obstacles(
+    mut boids: ResMut<Boids>,
+    obstacles: Query<&Obstacle>,
+    changed: Query<(), Changed<Obstacle>>,
+    mut removed: RemovedComponents<Obstacle>,
+) {
+    if changed.is_empty() && removed.read().next().is_none() {
+        return;
+    }
+    boids.obstacles = obstacles.iter().cloned().collect();
+}
+
+/// give new circle and box obstacles a mesh
+fn add_obstacle_meshes(
+    mut commands: Commands,
+    obstacles: Query<(Entity, &Obstacle), Added<Obstacle>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, obstacle) in &obstacles {
+        let (mesh, center): (Mesh, Vec2) = match obstacle {
+            Obstacle::Circle { center, radius } => (shape::Circle::new(*radius).into(), *center),
+            Obstacle::Box { min, max } => {
+                (shape::Quad::new(*max - *min).into(), (*min + *max) / 2.)
+            }
+            Obstacle::Polyline { .. } => continue,
+        };
+        commands.entity(entity).insert(MaterialMesh2dBundle {
+            mesh: meshes.add(mesh).into(),
+            material: materials.add(ColorMaterial::from(OBSTACLE_COLOR)),
+            // behind the boids
+            transform: Transform::from_translation(center.extend(-1.)),
+            ..default()
+        });
+    }
+}
+
+/// draw the polyline obstacles, which have no mesh
+fn draw_polylines(mut gizmos: Gizmos, obstacles: Query<&Obstacle>) {
+    for obstacle in &obstacles {
+        if let Obstacle::Polyline { points } = obstacle {
+            gizmos.linestrip_2d(points.iter().copied(), OBSTACLE_COLOR);
+        }
+    }
+}
+
+/// a few obstacles to fly around
+fn add_obstacles(mut commands: Commands) {
+    commands.spawn(Obstacle::Circle {
+        center: Vec2::new(-250., 50.),
+        radius: 40.,
+    });
+    commands.spawn(Obstacle::Box {
+        min: Vec2::new(150., -120.),
+        max: Vec2::new(230., -40.),
+    });
+    commands.spawn(Obstacle::Polyline {
+        points: vec![
+            Vec2::new(0., 180.),
+            Vec2::new(60., 120.),
+            Vec2::new(140., 140.),
+        ],
+    });
+}
+
+pub struct ObstaclePlugin;
+
+impl Plugin for ObstaclePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, add_obstacles).add_systems(
+            Update,
+            (sync_obstacles, add_obstacle_meshes, draw_polylines),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Arena;
+
+    #[test]
+    fn circle_is_hit_from_outside_and_left_from_inside() {
+        let circle = Obstacle::Circle {
+            center: Vec2::new(10., 0.),
+            radius: 2.,
+        };
+        let hit = circle.raycast(Vec2::ZERO, Vec2::X, 100.).unwrap();
+        assert!((hit.distance - 8.).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(Vec2::NEG_X, 1e-5));
+        assert_eq!(circle.raycast(Vec2::ZERO, Vec2::X, 5.), None);
+        assert_eq!(circle.raycast(Vec2::ZERO, Vec2::NEG_X, 100.), None);
+
+        let hit = circle.raycast(Vec2::new(11., 0.), Vec2::NEG_X, 1.).unwrap();
+        assert_eq!(hit.distance, 0.);
+        assert!(hit.normal.abs_diff_eq(Vec2::X, 1e-5));
+    }
+
+    #[test]
+    fn box_is_hit_on_the_entered_side() {
+        let aabb = Obstacle::Box {
+            min: Vec2::new(5., -1.),
+            max: Vec2::new(7., 1.),
+        };
+        let hit = aabb.raycast(Vec2::ZERO, Vec2::X, 100.).unwrap();
+        assert!((hit.distance - 5.).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec2::NEG_X);
+
+        let hit = aabb.raycast(Vec2::new(6., 10.), Vec2::NEG_Y, 100.).unwrap();
+        assert!((hit.distance - 9.).abs() < 1e-5);
+        assert_eq!(hit.normal, Vec2::Y);
+
+        assert_eq!(aabb.raycast(Vec2::ZERO, Vec2::Y, 100.), None);
+        assert_eq!(
+            aabb.raycast(Vec2::new(6.9, 0.), Vec2::NEG_X, 1.)
+                .unwrap()
+                .normal,
+            Vec2::X
+        );
+    }
+
+    #[test]
+    fn polyline_normal_faces_the_ray() {
+        let wall = Obstacle::Polyline {
+            points: vec![Vec2::new(3., -5.), Vec2::new(3., 5.), Vec2::new(8., 5.)],
+        };
+        let hit = wall.raycast(Vec2::ZERO, Vec2::X, 100.).unwrap();
+        assert!((hit.distance - 3.).abs() < 1e-5);
+        assert!(hit.normal.abs_diff_eq(Vec2::NEG_X, 1e-5));
+        let hit = wall.raycast(Vec2::new(6., 0.), Vec2::NEG_X, 100.).unwrap();
+        assert!(hit.normal.abs_diff_eq(Vec2::X, 1e-5));
+        assert_eq!(wall.raycast(Vec2::new(0., 6.), Vec2::NEG_X, 100.), None);
+    }
+
+    #[test]
+    fn boids_swerve_around_obstacles_ahead() {
+        let params = FlockParams::default();
+        let obstacles = [Obstacle::Circle {
+            center: Vec2::new(2., 40.),
+            radius: 10.,
+        }];
+        let surroundings = Surroundings {
+            params: &params,
+            arena: &Arena::default(),
+            obstacles: &obstacles,
+            predators: &[],
+            lure: None,
+        };
+        let boid = Boid::new(Vec3::ZERO, Vec3::Y * params.max_speed);
+        let force = ObstacleAvoidance.steer(&boid, &[], &surroundings);
+        // the obstacle is a bit to the right, so turn left and slow down
+        assert!(force.x < 0. && force.y < 0.);
+
+        let clear = Boid::new(Vec3::ZERO, Vec3::NEG_Y * params.max_speed);
+        assert_eq!(
+            ObstacleAvoidance.steer(&clear, &[], &surroundings),
+            Vec3::ZERO
+        );
+    }
+
+    #[test]
+    fn flock_stays_out_of_obstacles() {
+        use crate::flock::Flock;
+        use crate::steering::SteeringBehaviors;
+
+        let params = FlockParams::default();
+        let behaviors = SteeringBehaviors::default();
+        let mut flock = Flock::with_seed(7);
+        flock.obstacles.push(Obstacle::Circle {
+            center: Vec2::ZERO,
+            radius: 80.,
+        });
+        flock.add_random_boids(300, &params);
+        for _ in 0..600 {
+            flock.step(&params, &behaviors, 1. / 60.);
+        }
+        let inside = flock
+            .boids
+            .iter()
+            .filter(|b| b.position.truncate().length() < 75.)
+            .count();
+        assert_eq!(inside, 0);
+    }
+}
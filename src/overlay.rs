@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+use crate::camera::Selected;
+use crate::flock::{neighbors_among, Boid};
+use crate::params::FlockParams;
+use crate::plugin::{draw_boids, Boids};
+use crate::steering::SteeringBehaviors;
+
+/// colors of the forces of the steering behaviors, in the order they are
+/// registered; for the default ones separation, alignment, cohesion, obstacle
+/// avoidance, fleeing, edge avoidance, following the lure and the relations
+/// between species
+const FORCE_COLORS: [Color; 8] = [
+    Color::RED,
+    Color::YELLOW,
+    Color::LIME_GREEN,
+    Color::FUCHSIA,
+    Color::ORANGE,
+    Color::CYAN,
+    Color::VIOLET,
+    Color::PINK,
+];
+
+/// draw what the selected boid sees and how each behavior steers it: the
+/// neighbor distance, the view cone, lines to the neighbors and the weighted
+/// forces, scaled so that the maximum force is as long as the neighbor distance;
+/// all of it around where the boid is drawn, between two ticks
+fn draw_selected(
+    selected: Query<(&Boid, &Transform), With<Selected>>,
+    boids: Query<&Boid>,
+    flock: Res<Boids>,
+    params: Res<FlockParams>,
+    behaviors: Res<SteeringBehaviors>,
+    mut gizmos: Gizmos,
+) {
+    let Ok((me, transform)) = selected.get_single() else {
+        return;
+    };
+    let position = transform.translation;
+    let shift = position - me.position;
+    let radius = params.neighbor_distance;
+    gizmos.circle(position, Vec3::Z, radius, Color::GRAY);
+
+    // the view cone, unless it is the whole circle
+    if params.neighbor_angle < std::f32::consts::PI {
+        let heading = me.velocity.truncate().normalize_or_zero();
+        let direction_angle = heading.x.atan2(heading.y);
+        gizmos.arc_2d(
+            position.truncate(),
+            direction_angle,
+            params.neighbor_angle * 2.,
+            radius,
+            Color::WHITE,
+        );
+        for side in [-1., 1.] {
+            let edge = Vec2::from_angle(side * params.neighbor_angle).rotate(heading);
+            gizmos.line(position, position + edge.extend(0.) * radius, Color::WHITE);
+        }
+    }
+
+    let neighbors = neighbors_among(me, &boids, &flock.arena, &params);
+    for neighbor in &neighbors {
+        gizmos.line(
+            position,
+            neighbor.position + shift,
+            Color::rgba(1., 1., 1., 0.4),
+        );
+    }
+
+    if params.max_force <= 0. {
+        // no force at all, and no scale for them
+        return;
+    }
+    let surroundings = flock.surroundings(&params);
+    let scale = radius / params.max_force;
+    let forces = behaviors.forces(me, &neighbors, &surroundings);
+    for (force, color) in forces.iter().zip(FORCE_COLORS.iter().cycle()) {
+        gizmos.ray(position, *force * scale, *color);
+    }
+    gizmos.ray(position, forces.iter().sum::<Vec3>() * scale, Color::WHITE);
+}
+
+/// gizmos showing the neighborhood of the selected boid and the forces on it
+pub struct OverlayPlugin;
+
+impl Plugin for OverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_selected.after(draw_boids));
+    }
+}
@@ -0,0 +1,230 @@
+use std::ops::RangeInclusive;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use clap::ValueEnum;
+
+use crate::clock::SimulationClock;
+use crate::coloring::ColorMode;
+use crate::flock::Boid;
+use crate::params::FlockParams;
+use crate::pointer::PointerOverUi;
+use crate::spawn::{spawn_boids, DespawnBoids, SpawnBoids};
+
+/// a flock parameter tuned with a slider
+struct Tunable {
+    name: &'static str,
+    field: fn(&mut FlockParams) -> &mut f32,
+    step: f32,
+    min: f32,
+    max: f32,
+}
+
+/// indices of the speed limits in `TUNABLES`, which must not cross
+const MIN_SPEED: usize = 0;
+const MAX_SPEED: usize = 1;
+
+const TUNABLES: [Tunable; 8] = [
+    Tunable {
+        name: "min speed",
+        field: |p| &mut p.min_speed,
+        step: 5.,
+        min: 0.,
+        max: 500.,
+    },
+    Tunable {
+        name: "max speed",
+        field: |p| &mut p.max_speed,
+        step: 5.,
+        min: 5.,
+        max: 500.,
+    },
+    Tunable {
+        name: "neighbor distance",
+        field: |p| &mut p.neighbor_distance,
+        step: 5.,
+        min: 5.,
+        max: 200.,
+    },
+    Tunable {
+        name: "view angle",
+        field: |p| &mut p.neighbor_angle,
+        step: 0.1,
+        min: 0.1,
+        max: std::f32::consts::PI,
+    },
+    Tunable {
+        name: "separation distance",
+        field: |p| &mut p.separation_distance,
+        step: 5.,
+        min: 0.,
+        max: 200.,
+    },
+    Tunable {
+        name: "separation",
+        field: |p| &mut p.separation_weight,
+        step: 0.1,
+        min: 0.,
+        max: 10.,
+    },
+    Tunable {
+        name: "alignment",
+        field: |p| &mut p.alignment_weight,
+        step: 0.1,
+        min: 0.,
+        max: 10.,
+    },
+    Tunable {
+        name: "cohesion",
+        field: |p| &mut p.cohesion_weight,
+        step: 0.1,
+        min: 0.,
+        max: 10.,
+    },
+];
+
+/// the largest boid count the slider offers, unless there are more boids already
+const MAX_BOIDS: usize = 5000;
+
+/// the values the slider of the tunable with this index offers
+fn range(index: usize, params: &FlockParams) -> RangeInclusive<f32> {
+    let tunable = &TUNABLES[index];
+    // `Flock::update_velocities` clamps the speed between the limits
+    match index {
+        MIN_SPEED => tunable.min..=tunable.max.min(params.max_speed),
+        MAX_SPEED => tunable.min.max(params.min_speed)..=tunable.max,
+        _ => tunable.min..=tunable.max,
+    }
+}
+
+/// set the tunable with this index, within its range
+fn tune(params: &mut FlockParams, index: usize, value: f32) {
+    let range = range(index, params);
+    *(TUNABLES[index].field)(params) = value.clamp(*range.start(), *range.end());
+}
+
+/// whether the panel is shown, toggled with Tab
+#[derive(Resource)]
+struct PanelVisible(bool);
+
+fn show_panel(
+    mut contexts: EguiContexts,
+    (visible, mut over_ui): (Res<PanelVisible>, ResMut<PointerOverUi>),
+    mut params: ResMut<FlockParams>,
+    (mut clock, mut colors): (ResMut<SimulationClock>, ResMut<ColorMode>),
+    boids: Query<(), With<Boid>>,
+    (mut spawn, mut despawn): (EventWriter<SpawnBoids>, EventWriter<DespawnBoids>),
+) {
+    let ctx = contexts.ctx_mut();
+    over_ui.0 = visible.0 && (ctx.is_pointer_over_area() || ctx.wants_pointer_input());
+    if !visible.0 {
+        return;
+    }
+    egui::Window::new("flock")
+        .default_pos([10., 10.])
+        .resizable(false)
+        .show(ctx, |ui| {
+            for (i, tunable) in TUNABLES.iter().enumerate() {
+                // only a moved slider counts as a change of the parameters
+                let mut value = *(tunable.field)(params.bypass_change_detection());
+                let slider = egui::Slider::new(&mut value, range(i, &params))
+                    .step_by(tunable.step as f64)
+                    .text(tunable.name);
+                if ui.add(slider).changed() {
+                    tune(&mut params, i, value);
+                }
+            }
+
+            let count = boids.iter().count();
+            let mut target = count;
+            ui.add(egui::Slider::new(&mut target, 0..=MAX_BOIDS.max(count)).text("boids"));
+            if target > count {
+                spawn.send(SpawnBoids {
+                    count: target - count,
+                    region: None,
+                    heading: None,
+                    species: None,
+                });
+            } else if target < count {
+                despawn.send(DespawnBoids {
+                    region: None,
+                    count: Some(count - target),
+                });
+            }
+
+            ui.horizontal(|ui| {
+                let label = if clock.paused { "resume" } else { "pause" };
+                if ui.button(label).clicked() {
+                    clock.paused = !clock.paused;
+                }
+                if ui.button("step").clicked() {
+                    clock.step();
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("-").clicked() {
+                    clock.slower();
+                }
+                ui.label(format!("speed: {}x", clock.speed));
+                if ui.button("+").clicked() {
+                    clock.faster();
+                }
+            });
+            egui::ComboBox::from_label("colors")
+                .selected_text(format!("{:?}", *colors).to_lowercase())
+                .show_ui(ui, |ui| {
+                    for &mode in ColorMode::value_variants() {
+                        let label = format!("{mode:?}").to_lowercase();
+                        if ui.selectable_label(*colors == mode, label).clicked() {
+                            *colors = mode;
+                        }
+                    }
+                });
+        });
+}
+
+/// show or hide the panel with Tab
+fn toggle_panel(keys: Res<Input<KeyCode>>, mut visible: ResMut<PanelVisible>) {
+    if keys.just_pressed(KeyCode::Tab) {
+        visible.0 = !visible.0;
+    }
+}
+
+/// sliders to tune the flock parameters and the number of boids, and buttons
+/// to pause the simulation or change its speed
+pub struct PanelPlugin;
+
+impl Plugin for PanelPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.insert_resource(PanelVisible(true)).add_systems(
+            Update,
+            // the boid count is up to date in the next frame
+            (show_panel.before(spawn_boids), toggle_panel),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_limits_do_not_cross() {
+        let mut params = FlockParams::default();
+        tune(&mut params, MIN_SPEED, 500.);
+        assert_eq!(params.min_speed, params.max_speed);
+        assert_eq!(range(MAX_SPEED, &params).start(), &params.min_speed);
+
+        tune(&mut params, MAX_SPEED, 0.);
+        assert_eq!(params.max_speed, FlockParams::default().max_speed);
+
+        let mut params = FlockParams::default();
+        tune(&mut params, MAX_SPEED, 0.);
+        assert_eq!(params.max_speed, params.min_speed);
+        tune(&mut params, MIN_SPEED, 0.);
+        assert_eq!(params.min_speed, 0.);
+    }
+}
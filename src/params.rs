@@ -0,0 +1,247 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::boundary::BoundaryMode;
+use crate::species::{Relation, Species, SpeciesParams};
+
+/// tunable parameters of the flocking rules
+///
+/// Also an asset, so that the config file can be hot reloaded, see `config`.
+#[derive(Asset, TypePath, Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlockParams {
+    /// boids never fly slower than this, in units per second
+    pub min_speed: f32,
+    /// boids never fly faster than this, in units per second
+    pub max_speed: f32,
+    /// limit of each steering force, in units per second squared
+    pub max_force: f32,
+    /// boids closer than this are neighbors (if they are also in the view angle)
+    pub neighbor_distance: f32,
+    /// half of the view angle of a boid, in radians
+    pub neighbor_angle: f32,
+    /// neighbors closer than this are avoided
+    pub separation_distance: f32,
+    /// weight of the force away from the neighbors that are too close
+    pub separation_weight: f32,
+    /// weight of the force towards the average heading of the neighbors
+    pub alignment_weight: f32,
+    /// weight of the force towards the center of the neighbors
+    pub cohesion_weight: f32,
+    /// how far ahead boids look for obstacles
+    pub obstacle_look_ahead: f32,
+    /// weight of the force swerving around obstacles
+    pub obstacle_weight: f32,
+    /// speed of the predators, in units per second
+    pub predator_speed: f32,
+    /// boids closer than this to a predator flee from it
+    pub panic_distance: f32,
+    /// weight of the force away from the predators
+    pub flee_weight: f32,
+    /// what happens to boids at the edges of the arena
+    pub boundary: BoundaryMode,
+    /// in soft boundary mode, boids closer than this to an edge turn back
+    pub edge_margin: f32,
+    /// weight of the force away from the edges in soft boundary mode
+    pub edge_weight: f32,
+    /// boids closer than this to the mouse are attracted or repelled by it
+    pub lure_radius: f32,
+    /// weight of the force towards or away from the mouse
+    pub lure_weight: f32,
+    /// the species of the flock, the boids are spread evenly over them
+    pub species: Vec<SpeciesParams>,
+    /// how the boids of each species treat the ones of every species, by row;
+    /// missing entries are `Flock`
+    pub relations: Vec<Vec<Relation>>,
+    /// weight of the forces avoiding and chasing other species
+    pub species_weight: f32,
+}
+
+impl Default for FlockParams {
+    fn default() -> Self {
+        Self {
+            min_speed: 60.,
+            max_speed: 100.,
+            max_force: 120.,
+            neighbor_distance: 50.,
+            neighbor_angle: 2.79, // 160.0.to_radians();
+            separation_distance: 25.,
+            separation_weight: 1.5,
+            alignment_weight: 1.,
+            cohesion_weight: 1.,
+            obstacle_look_ahead: 60.,
+            obstacle_weight: 4.,
+            predator_speed: 110.,
+            panic_distance: 80.,
+            flee_weight: 3.,
+            boundary: BoundaryMode::Bounce,
+            edge_margin: 50.,
+            edge_weight: 3.,
+            lure_radius: 150.,
+            lure_weight: 2.,
+            species: vec![SpeciesParams::default()],
+            relations: Vec::new(),
+            species_weight: 2.,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParamsError {
+    #[error("could not read the flock parameters: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("flock parameters are not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("invalid RON flock parameters: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid TOML flock parameters: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("unknown flock parameters format {0:?}, expected `ron` or `toml`")]
+    UnknownFormat(String),
+    #[error("invalid flock parameters: {0}")]
+    Invalid(String),
+}
+
+impl FlockParams {
+    pub fn neighbor_distance_squared(&self) -> f32 {
+        self.neighbor_distance * self.neighbor_distance
+    }
+
+    /// the parameters of a species, the default ones if there is no such species
+    pub fn species_params(&self, species: Species) -> SpeciesParams {
+        self.species.get(species.0).copied().unwrap_or_default()
+    }
+
+    /// how boids of the species `me` treat their neighbors of the species `other`
+    pub fn relation(&self, me: Species, other: Species) -> Relation {
+        self.relations
+            .get(me.0)
+            .and_then(|row| row.get(other.0))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// check the values the simulation cannot run with
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        let invalid = |message: String| Err(ParamsError::Invalid(message));
+        let values = [
+            ("min_speed", self.min_speed),
+            ("max_speed", self.max_speed),
+            ("max_force", self.max_force),
+            ("neighbor_distance", self.neighbor_distance),
+            ("neighbor_angle", self.neighbor_angle),
+            ("separation_distance", self.separation_distance),
+            ("obstacle_look_ahead", self.obstacle_look_ahead),
+            ("predator_speed", self.predator_speed),
+            ("panic_distance", self.panic_distance),
+            ("edge_margin", self.edge_margin),
+            ("lure_radius", self.lure_radius),
+        ];
+        for (name, value) in values {
+            if !(value >= 0. && value.is_finite()) {
+                return invalid(format!("{name} must be a finite number >= 0, not {value}"));
+            }
+        }
+        if self.min_speed > self.max_speed {
+            return invalid(format!(
+                "min_speed {} is above max_speed {}",
+                self.min_speed, self.max_speed
+            ));
+        }
+        // the spatial grid has cells of this size
+        if self.neighbor_distance == 0. {
+            return invalid("neighbor_distance must be above 0".to_owned());
+        }
+        for (i, species) in self.species.iter().enumerate() {
+            if !(species.speed > 0. && species.speed.is_finite()) {
+                return invalid(format!("speed of species {i} must be above 0"));
+            }
+            if !(species.agility >= 0. && species.agility.is_finite()) {
+                return invalid(format!("agility of species {i} must be >= 0"));
+            }
+        }
+        Ok(())
+    }
+
+    /// parse and validate the parameters, `extension` selects the format
+    pub fn parse(text: &str, extension: &str) -> Result<Self, ParamsError> {
+        let params: Self = match extension {
+            "ron" => ron::from_str(text)?,
+            "toml" => toml::from_str(text)?,
+            _ => return Err(ParamsError::UnknownFormat(extension.to_owned())),
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// read the parameters from a `.ron` or `.toml` file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ParamsError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default();
+        Self::parse(&std::fs::read_to_string(path)?, extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_keep_their_defaults() {
+        let params = FlockParams::parse("(max_speed: 142.)", "ron").unwrap();
+        assert_eq!(params.max_speed, 142.);
+        assert_eq!(
+            params.neighbor_distance,
+            FlockParams::default().neighbor_distance
+        );
+
+        let params = FlockParams::parse("cohesion_weight = 3.0", "toml").unwrap();
+        assert_eq!(params.cohesion_weight, 3.);
+        assert_eq!(params.max_speed, FlockParams::default().max_speed);
+    }
+
+    #[test]
+    fn shipped_config_matches_defaults() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/flock.ron");
+        assert_eq!(FlockParams::load(path).unwrap(), FlockParams::default());
+    }
+
+    #[test]
+    fn species_example_loads() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/species.ron");
+        let params = FlockParams::load(path).unwrap();
+        assert_eq!(params.species.len(), 3);
+        assert_eq!(params.relation(Species(2), Species(0)), Relation::Chase);
+    }
+
+    #[test]
+    fn values_the_simulation_cannot_run_with_are_rejected() {
+        for text in [
+            "(min_speed: 150.)",
+            "(neighbor_distance: 0.)",
+            "(max_force: -1.)",
+            "(species: [(speed: -1.)])",
+        ] {
+            assert!(
+                matches!(
+                    FlockParams::parse(text, "ron"),
+                    Err(ParamsError::Invalid(_))
+                ),
+                "{text} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_format_is_rejected() {
+        assert!(matches!(
+            FlockParams::parse("", "json"),
+            Err(ParamsError::UnknownFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,323 @@
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::window::PrimaryWindow;
+
+use crate::boundary::{Arena, Dimensions};
+use crate::camera::CameraPlugin;
+use crate::clock::{ClockPlugin, SimulationClock};
+use crate::coloring::{ColorMode, ColoringPlugin, Neighborhood};
+use crate::config::FlockConfigPlugin;
+use crate::flock::{Boid, Flock};
+use crate::instancing::InstancingPlugin;
+use crate::lure::LurePlugin;
+use crate::obstacle::ObstaclePlugin;
+use crate::overlay::OverlayPlugin;
+use crate::panel::PanelPlugin;
+use crate::params::FlockParams;
+use crate::pointer::PointerPlugin;
+use crate::predator::{Predator, PredatorPlugin};
+use crate::spawn::{SpawnBoids, SpawnPlugin};
+use crate::species::redistribute_species;
+use crate::steering::SteeringBehaviors;
+use crate::trails::TrailsPlugin;
+use crate::view3d::View3dPlugin;
+
+/// the simulation state besides the boids and the predators, which are the
+/// `Boid` and `Predator` components; they are copied into the flock for every step
+#[derive(Resource, Deref, DerefMut)]
+pub struct Boids(pub Flock);
+
+/// whether the arena follows the size of the window or is fitted into it
+#[derive(Resource, Clone, Copy, PartialEq)]
+enum WorldSize {
+    Window,
+    Fixed(Vec2),
+}
+
+fn add_camera(world_size: Res<WorldSize>, mut commands: Commands) {
+    let mut camera = Camera2dBundle::default();
+    if let WorldSize::Fixed(size) = *world_size {
+        // show the whole arena whatever the shape of the window
+        camera.projection.scaling_mode = ScalingMode::AutoMin {
+            min_width: size.x,
+            min_height: size.y,
+        };
+    }
+    commands.spawn(camera);
+}
+
+/// make the arena cover the primary window, one unit per logical pixel
+fn fit_arena_to_window(
+    windows: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    mut arena: ResMut<Arena>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let half_size = Vec3::new(window.width(), window.height(), 0.) / 2.;
+    // `Changed` also fires for moves and focus changes of the window
+    if arena.half_size != half_size && half_size.truncate().min_element() > 0. {
+        arena.half_size = half_size;
+    }
+}
+
+/// hand a changed arena over to the flock
+fn sync_arena(arena: Res<Arena>, mut boids: ResMut<Boids>) {
+    if arena.is_changed() {
+        boids.arena = *arena;
+    }
+}
+
+/// initialize the scene with a bunch of boids
+fn add_boids(mut spawn: EventWriter<SpawnBoids>) {
+    spawn.send(SpawnBoids {
+        count: 300,
+        region: None,
+        heading: None,
+        species: None,
+    });
+}
+
+/// advance the flock by one fixed tick, and update the `Neighborhood` of the
+/// boids; the clusters are only looked for in the `Cluster` color mode
+pub(crate) fn step_boids(
+    mut flock: ResMut<Boids>,
+    mut boids: Query<(&mut Boid, Option<&mut Neighborhood>)>,
+    mut predators: Query<&mut Predator>,
+    (params, behaviors): (Res<FlockParams>, Res<SteeringBehaviors>),
+    mode: Option<Res<ColorMode>>,
+    mut clock: ResMut<SimulationClock>,
+    time: Res<Time>,
+) {
+    if !clock.tick() {
+        // nothing moves, so that `draw_boids` has nothing to interpolate
+        for (mut boid, _) in &mut boids {
+            boid.save_previous();
+        }
+        for mut predator in &mut predators {
+            predator.body.save_previous();
+        }
+        return;
+    }
+    let flock = &mut flock.0;
+    flock.boids.clear();
+    flock.boids.extend(boids.iter().map(|(boid, _)| *boid));
+    flock.predators.clear();
+    flock.predators.extend(predators.iter().copied());
+
+    flock.step(&params, &behaviors, time.delta_seconds());
+
+    let clusters = match mode.as_deref() {
+        Some(ColorMode::Cluster) => flock.clusters(&params),
+        _ => Vec::new(),
+    };
+
+    // the queries iterate in the same order as long as no entity is added or removed
+    for (i, ((mut boid, neighborhood), stepped)) in boids.iter_mut().zip(&flock.boids).enumerate() {
+        *boid = *stepped;
+        if let Some(mut neighborhood) = neighborhood {
+            neighborhood.count = flock.neighbor_counts[i];
+            if let Some(&cluster) = clusters.get(i) {
+                neighborhood.cluster = cluster;
+            }
+        }
+    }
+    for (mut predator, stepped) in predators.iter_mut().zip(&flock.predators) {
+        *predator = *stepped;
+    }
+}
+
+/// switch to the next boundary mode with the B key
+fn cycle_boundary(keys: Res<Input<KeyCode>>, mut params: ResMut<FlockParams>) {
+    if keys.just_pressed(KeyCode::B) {
+        params.boundary = params.boundary.next();
+        info!("boundary mode: {:?}", params.boundary);
+    }
+}
+
+/// update the position of the boid sprites, interpolating between the last
+/// two ticks by how far the frame is into the next tick
+pub(crate) fn draw_boids(time: Res<Time<Fixed>>, mut query: Query<(&Boid, &mut Transform)>) {
+    let alpha = time.overstep_percentage();
+    for (boid, mut transform) in &mut query {
+        let (position, rotation) = boid.interpolated(alpha);
+        transform.translation = position;
+        transform.rotation = rotation;
+    }
+}
+
+pub struct BoidsPlugin {
+    /// path of the flock config file, relative to the assets folder
+    pub config: String,
+    /// seed of the simulation, random if not given
+    pub seed: Option<u64>,
+    /// simulation ticks per second
+    pub tick_rate: f64,
+    /// number of predators hunting the flock
+    pub predators: usize,
+    /// whether the boids fly in a plane or in a volume
+    pub dimensions: Dimensions,
+    /// size of the arena, fitted into the window in 2D; the flat arena covers
+    /// the window and follows its size if not given, the depth is only used in 3D
+    pub world_size: Option<Vec3>,
+}
+
+impl Default for BoidsPlugin {
+    fn default() -> Self {
+        Self {
+            config: "flock.ron".to_owned(),
+            seed: None,
+            tick_rate: 60.,
+            predators: 1,
+            dimensions: Dimensions::Two,
+            world_size: None,
+        }
+    }
+}
+
+impl Plugin for BoidsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            FlockConfigPlugin {
+                path: self.config.clone(),
+            },
+            PredatorPlugin {
+                count: self.predators,
+            },
+            ClockPlugin,
+            ColoringPlugin,
+            PointerPlugin,
+            SpawnPlugin,
+            LurePlugin,
+            PanelPlugin,
+            TrailsPlugin,
+        ))
+        .insert_resource(Boids(self.seed.map_or_else(Flock::new, Flock::with_seed)))
+        .init_resource::<SteeringBehaviors>()
+        .insert_resource(Time::<Fixed>::from_hz(self.tick_rate))
+        .insert_resource(self.dimensions)
+        .add_systems(Startup, add_boids)
+        .add_systems(FixedUpdate, step_boids)
+        .add_systems(Update, (draw_boids, cycle_boundary, redistribute_species));
+
+        let arena = match (self.dimensions, self.world_size) {
+            (Dimensions::Two, Some(size)) => Arena::new(size.truncate().extend(0.)),
+            (Dimensions::Three, Some(size)) => Arena::new(size),
+            (dimensions, None) => dimensions.default_arena(),
+        };
+        let world_size = match self.world_size {
+            None if self.dimensions == Dimensions::Two => WorldSize::Window,
+            _ => WorldSize::Fixed(arena.half_size.truncate() * 2.),
+        };
+        app.insert_resource(world_size).insert_resource(arena);
+        match self.dimensions {
+            // the obstacles are drawn as flat shapes, so there are only some in 2D
+            Dimensions::Two => app
+                .add_plugins((
+                    ObstaclePlugin,
+                    CameraPlugin,
+                    OverlayPlugin,
+                    InstancingPlugin,
+                ))
+                .add_systems(Startup, add_camera),
+            Dimensions::Three => app.add_plugins(View3dPlugin),
+        };
+        let resize = || {
+            (
+                fit_arena_to_window.run_if(resource_equals(WorldSize::Window)),
+                sync_arena,
+            )
+                .chain()
+        };
+        // the boids and predators spawned at startup need the right arena already
+        app.add_systems(PreStartup, resize())
+            .add_systems(PreUpdate, resize());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn boid_entities_are_stepped_and_can_be_despawned() {
+        let mut world = World::new();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_millis(100));
+        world.insert_resource(time);
+        world.insert_resource(FlockParams::default());
+        world.insert_resource(Boids(Flock::with_seed(0)));
+        world.init_resource::<SteeringBehaviors>();
+        world.init_resource::<SimulationClock>();
+
+        // far apart, so that they do not steer each other
+        let left = world
+            .spawn(Boid::new(Vec3::new(-100., 0., 0.), Vec3::Y * 80.))
+            .id();
+        let right = world
+            .spawn(Boid::new(Vec3::new(100., 0., 0.), Vec3::NEG_Y * 80.))
+            .id();
+        world.run_system_once(step_boids);
+        assert_eq!(
+            world.get::<Boid>(left).unwrap().position,
+            Vec3::new(-100., 8., 0.)
+        );
+
+        world.despawn(left);
+        world.run_system_once(step_boids);
+        assert_eq!(world.resource::<Boids>().boids.len(), 1);
+        assert_eq!(
+            world.get::<Boid>(right).unwrap().position,
+            Vec3::new(100., -16., 0.)
+        );
+    }
+
+    #[test]
+    fn neighborhoods_stay_with_their_boids() {
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.insert_resource(FlockParams::default());
+        world.insert_resource(Boids(Flock::with_seed(0)));
+        world.insert_resource(ColorMode::Cluster);
+        world.init_resource::<SteeringBehaviors>();
+        world.init_resource::<SimulationClock>();
+
+        let pair = [-100., -90.].map(|x| {
+            world
+                .spawn((
+                    Boid::new(Vec3::new(x, 0., 0.), Vec3::X * 80.),
+                    Neighborhood::default(),
+                ))
+                .id()
+        });
+        let alone = world
+            .spawn((
+                Boid::new(Vec3::new(200., 0., 0.), Vec3::X * 80.),
+                Neighborhood::default(),
+            ))
+            .id();
+        world.run_system_once(step_boids);
+        let neighborhood = |world: &World, entity| *world.get::<Neighborhood>(entity).unwrap();
+        let before = pair.map(|entity| neighborhood(&world, entity));
+        assert_eq!(neighborhood(&world, alone).count, 0);
+        assert_eq!(before[0].cluster, before[1].cluster);
+        assert_ne!(before[0].cluster, neighborhood(&world, alone).cluster);
+
+        // moving entities to another archetype reorders the query
+        world.entity_mut(pair[0]).insert(Name::new("selected"));
+        world.run_system_once(step_boids);
+        assert_eq!(neighborhood(&world, alone).count, 0);
+        assert_eq!(
+            neighborhood(&world, pair[0]).cluster,
+            neighborhood(&world, pair[1]).cluster
+        );
+        assert_ne!(
+            neighborhood(&world, pair[0]).cluster,
+            neighborhood(&world, alone).cluster
+        );
+    }
+}
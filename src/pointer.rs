@@ -0,0 +1,41 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+/// where the mouse cursor points in the world, on the plane z = 0;
+/// `None` when the cursor is outside of the window or over the UI
+#[derive(Resource, Default, Deref)]
+pub struct Pointer(pub Option<Vec3>);
+
+/// whether the cursor is over the UI, e.g. the panel, so that its clicks are
+/// not meant for the world
+#[derive(Resource, Default)]
+pub struct PointerOverUi(pub bool);
+
+fn track_pointer(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    over_ui: Res<PointerOverUi>,
+    mut pointer: ResMut<Pointer>,
+) {
+    if over_ui.0 {
+        pointer.0 = None;
+        return;
+    }
+    pointer.0 = windows
+        .get_single()
+        .ok()
+        .and_then(Window::cursor_position)
+        .zip(cameras.get_single().ok())
+        .and_then(|(cursor, (camera, transform))| camera.viewport_to_world(transform, cursor))
+        .and_then(|ray| Some(ray.get_point(ray.intersect_plane(Vec3::ZERO, Vec3::Z)?)));
+}
+
+pub struct PointerPlugin;
+
+impl Plugin for PointerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Pointer>()
+            .init_resource::<PointerOverUi>()
+            .add_systems(PreUpdate, track_pointer);
+    }
+}
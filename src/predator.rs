@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use bevy::sprite::Mesh2dHandle;
+
+use crate::boundary::{Arena, Dimensions};
+use crate::flock::Boid;
+use crate::params::FlockParams;
+use crate::plugin::Boids;
+use crate::steering::{steer_towards, SteeringBehavior, Surroundings};
+
+/// a hunter chasing the nearest boid, always at `FlockParams::predator_speed`
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Predator {
+    /// position and velocity, moved like the boids
+    pub body: Boid,
+}
+
+impl Predator {
+    /// turn towards the nearest boid
+    pub fn chase(&mut self, boids: &[Boid], params: &FlockParams, arena: &Arena, dt: f32) {
+        let position = self.body.position;
+        let nearest = boids
+            .iter()
+            .map(|prey| arena.offset(params.boundary, position, prey.position))
+            .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+        let steering = match nearest {
+            Some(offset) => {
+                let desired = offset.normalize_or_zero() * params.predator_speed;
+                if desired == Vec3::ZERO {
+                    Vec3::ZERO
+                } else {
+                    (desired - self.body.velocity).clamp_length_max(params.max_force)
+                }
+            }
+            None => Vec3::ZERO,
+        };
+        let velocity = self.body.velocity + steering * dt;
+        self.body.velocity = velocity.normalize_or_zero() * params.predator_speed;
+    }
+}
+
+/// steer away from the predators within the panic distance, the closer the stronger
+pub struct Flee;
+
+impl SteeringBehavior for Flee {
+    fn steer(&self, boid: &Boid, _neighbors: &[Boid], surroundings: &Surroundings) -> Vec3 {
+        let params = surroundings.params;
+        let away = surroundings
+            .predators
+            .iter()
+            .map(|predator| {
+                let away = surroundings.arena.offset(
+                    params.boundary,
+                    predator.body.position,
+                    boid.position,
+                );
+                let distance = away.length();
+                if distance < params.panic_distance {
+                    away.normalize_or_zero() * (1. - distance / params.panic_distance)
+                } else {
+                    Vec3::ZERO
+                }
+            })
+            .sum::<Vec3>();
+        steer_towards(away, boid.velocity, params)
+    }
+
+    fn weight(&self, params: &FlockParams) -> f32 {
+        params.flee_weight
+    }
+}
+
+/// number of predators spawned at startup
+#[derive(Resource)]
+struct StartingPredators(usize);
+
+/// spawn the predators
+fn add_predators(
+    count: Res<StartingPredators>,
+    mut boids: ResMut<Boids>,
+    params: Res<FlockParams>,
+    mut commands: Commands,
+) {
+    for predator in boids.random_predators(count.0, &params) {
+        commands.spawn((
+            predator,
+            SpatialBundle::from_transform(
+                Transform::from_translation(predator.body.position)
+                    .with_rotation(predator.body.rotation()),
+            ),
+        ));
+    }
+}
+
+/// give the new predators a sprite
+fn add_predator_sprites(
+    query: Query<Entity, Added<Predator>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if query.is_empty() {
+        return;
+    }
+    let mesh = meshes.add(shape::RegularPolygon::new(10., 3).into());
+    let material = materials.add(ColorMaterial::from(Color::ORANGE_RED));
+    for entity in &query {
+        commands
+            .entity(entity)
+            .insert((Mesh2dHandle(mesh.clone()), material.clone()));
+    }
+}
+
+/// update the position of the predator sprites, see `draw_boids`
+fn draw_predators(
+    dimensions: Res<Dimensions>,
+    time: Res<Time<Fixed>>,
+    mut query: Query<(&Predator, &mut Transform)>,
+) {
+    // on top of the boids in 2D
+    let layer = match *dimensions {
+        Dimensions::Two => Vec3::Z,
+        Dimensions::Three => Vec3::ZERO,
+    };
+    let alpha = time.overstep_percentage();
+    for (predator, mut transform) in &mut query {
+        let (position, rotation) = predator.body.interpolated(alpha);
+        transform.translation = position + layer;
+        transform.rotation = rotation;
+    }
+}
+
+pub struct PredatorPlugin {
+    /// number of predators spawned at startup
+    pub count: usize,
+}
+
+impl Plugin for PredatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(StartingPredators(self.count))
+            .add_systems(Startup, add_predators)
+            .add_systems(
+                Update,
+                (
+                    add_predator_sprites.run_if(resource_equals(Dimensions::Two)),
+                    draw_predators,
+                ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Arena;
+    use crate::flock::Flock;
+    use crate::steering::SteeringBehaviors;
+
+    #[test]
+    fn boids_flee_within_the_panic_distance() {
+        let params = FlockParams::default();
+        let predators = [Predator {
+            body: Boid::new(Vec3::new(-10., 0., 0.), Vec3::X),
+        }];
+        let surroundings = Surroundings {
+            params: &params,
+            arena: &Arena::default(),
+            obstacles: &[],
+            predators: &predators,
+            lure: None,
+        };
+        let boid = Boid::new(Vec3::ZERO, Vec3::Y * params.max_speed);
+        assert!(Flee.steer(&boid, &[], &surroundings).x > 0.);
+
+        let far = Boid::new(
+            Vec3::new(params.panic_distance, 0., 0.),
+            Vec3::Y * params.max_speed,
+        );
+        assert_eq!(Flee.steer(&far, &[], &surroundings), Vec3::ZERO);
+    }
+
+    #[test]
+    fn predator_closes_in_on_a_lone_boid() {
+        let params = FlockParams {
+            flee_weight: 0.,
+            ..default()
+        };
+        let behaviors = SteeringBehaviors::default();
+        let mut flock = Flock::with_seed(0);
+        flock
+            .boids
+            .push(Boid::new(Vec3::ZERO, Vec3::X * params.min_speed));
+        flock.predators.push(Predator {
+            body: Boid::new(Vec3::new(-150., 0., 0.), Vec3::Y * params.predator_speed),
+        });
+        let start = flock.boids[0]
+            .position
+            .distance(flock.predators[0].body.position);
+        for _ in 0..300 {
+            flock.step(&params, &behaviors, 1. / 60.);
+        }
+        let end = flock.boids[0]
+            .position
+            .distance(flock.predators[0].body.position);
+        assert!(end < start / 2.);
+        let speed = flock.predators[0].body.velocity.length();
+        assert!((speed - params.predator_speed).abs() < 1e-3);
+    }
+}
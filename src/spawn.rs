@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+
+use crate::flock::Boid;
+use crate::params::FlockParams;
+use crate::plugin::Boids;
+use crate::pointer::Pointer;
+use crate::species::{RandomSpecies, Species};
+
+/// add boids to the flock
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct SpawnBoids {
+    pub count: usize,
+    /// center and radius of the disc (ball in 3D) the boids appear in,
+    /// anywhere in the arena if `None`
+    pub region: Option<(Vec3, f32)>,
+    /// direction the boids fly in at first, random if `None`
+    pub heading: Option<Vec3>,
+    /// species of all the boids, a random one for each if `None`
+    pub species: Option<Species>,
+}
+
+/// remove boids from the flock
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct DespawnBoids {
+    /// center and radius of the disc (ball in 3D) the boids are removed from,
+    /// all boids if `None`
+    pub region: Option<(Vec3, f32)>,
+    /// remove at most this many boids, all of those in the region if `None`
+    pub count: Option<usize>,
+}
+
+/// boids added or removed with one click
+const CLICK_COUNT: usize = 20;
+/// radius around the cursor the boids of a click are added to or removed from
+const CLICK_RADIUS: f32 = 30.;
+
+pub(crate) fn spawn_boids(
+    mut events: EventReader<SpawnBoids>,
+    mut flock: ResMut<Boids>,
+    params: Res<FlockParams>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        let boids = match event.region {
+            Some((center, radius)) => {
+                flock.random_boids_around(event.count, &params, center, radius)
+            }
+            None => flock.random_boids(event.count, &params),
+        };
+        for mut boid in boids {
+            if let Some(direction) = event.heading.and_then(Vec3::try_normalize) {
+                boid.velocity = direction * boid.velocity.length();
+                boid.save_previous();
+            }
+            if let Some(species) = event.species {
+                boid.species = species;
+            }
+            let mut entity = commands.spawn((
+                boid,
+                SpatialBundle::from_transform(
+                    Transform::from_translation(boid.position).with_rotation(boid.rotation()),
+                ),
+            ));
+            if event.species.is_none() {
+                entity.insert(RandomSpecies);
+            }
+        }
+    }
+}
+
+fn despawn_boids(
+    mut events: EventReader<DespawnBoids>,
+    boids: Query<(Entity, &Boid)>,
+    mut commands: Commands,
+) {
+    let mut despawned = Vec::new();
+    for event in events.read() {
+        let inside = boids
+            .iter()
+            .filter(|(entity, _)| !despawned.contains(entity))
+            .filter(|(_, boid)| match event.region {
+                Some((center, radius)) => boid.position.distance(center) <= radius,
+                None => true,
+            })
+            .map(|(entity, _)| entity)
+            .take(event.count.unwrap_or(usize::MAX))
+            .collect::<Vec<_>>();
+        despawned.extend(inside);
+    }
+    for entity in despawned {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// shift + left click adds boids at the cursor, shift + right click removes
+/// them, C removes all of them
+fn spawn_with_mouse(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<MouseButton>>,
+    pointer: Res<Pointer>,
+    mut spawn: EventWriter<SpawnBoids>,
+    mut despawn: EventWriter<DespawnBoids>,
+) {
+    if keys.just_pressed(KeyCode::C) {
+        despawn.send(DespawnBoids {
+            region: None,
+            count: None,
+        });
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let Some(cursor) = **pointer else {
+        return;
+    };
+    if shift && buttons.just_pressed(MouseButton::Left) {
+        spawn.send(SpawnBoids {
+            count: CLICK_COUNT,
+            region: Some((cursor, CLICK_RADIUS)),
+            heading: None,
+            species: None,
+        });
+    }
+    if shift && buttons.just_pressed(MouseButton::Right) {
+        despawn.send(DespawnBoids {
+            region: Some((cursor, CLICK_RADIUS)),
+            count: None,
+        });
+    }
+}
+
+/// the `SpawnBoids` and `DespawnBoids` events, and the mouse and key bindings sending them
+pub struct SpawnPlugin;
+
+impl Plugin for SpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnBoids>()
+            .add_event::<DespawnBoids>()
+            .add_systems(
+                Update,
+                (spawn_with_mouse, (spawn_boids, despawn_boids)).chain(),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flock::Flock;
+
+    #[test]
+    fn boids_are_spawned_and_despawned_by_region() {
+        let mut app = App::new();
+        app.add_event::<SpawnBoids>()
+            .add_event::<DespawnBoids>()
+            .insert_resource(Boids(Flock::with_seed(0)))
+            .init_resource::<FlockParams>()
+            .add_systems(Update, (spawn_boids, despawn_boids).chain());
+        let count = |app: &mut App| app.world.query::<&Boid>().iter(&app.world).count();
+
+        let left = Vec3::new(-200., 0., 0.);
+        app.world.send_event(SpawnBoids {
+            count: 10,
+            region: Some((left, 20.)),
+            heading: Some(Vec3::X),
+            species: Some(Species(1)),
+        });
+        app.world.send_event(SpawnBoids {
+            count: 5,
+            region: Some((-left, 20.)),
+            heading: None,
+            species: None,
+        });
+        app.update();
+        assert_eq!(count(&mut app), 15);
+        for boid in app.world.query::<&Boid>().iter(&app.world) {
+            if boid.position.x < 0. {
+                assert!(boid.position.distance(left) <= 20.);
+                assert!(boid.velocity.angle_between(Vec3::X) < 1e-3);
+                assert_eq!(boid.species, Species(1));
+            } else {
+                assert!(boid.position.distance(-left) <= 20.);
+            }
+        }
+
+        app.world.send_event(DespawnBoids {
+            region: Some((left, 20.)),
+            count: None,
+        });
+        app.update();
+        assert_eq!(count(&mut app), 5);
+        app.world.send_event(DespawnBoids {
+            region: None,
+            count: Some(2),
+        });
+        app.update();
+        assert_eq!(count(&mut app), 3);
+        app.world.send_event(DespawnBoids {
+            region: None,
+            count: None,
+        });
+        app.update();
+        assert_eq!(count(&mut app), 0);
+    }
+}
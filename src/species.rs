@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::flock::Boid;
+use crate::params::FlockParams;
+use crate::plugin::Boids;
+use crate::steering::{steer_towards, SteeringBehavior, Surroundings};
+
+/// which species a boid belongs to, an index into `FlockParams::species`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Species(pub usize);
+
+/// how the boids of one species treat their neighbors of another one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Relation {
+    /// separation, alignment and cohesion, as within a species
+    #[default]
+    Flock,
+    /// keep away from them
+    Avoid,
+    /// do not even see them
+    Ignore,
+    /// go after the nearest of them
+    Chase,
+}
+
+/// what sets the boids of a species apart
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeciesParams {
+    /// sRGB color of the boids
+    pub color: [f32; 3],
+    /// factor of the speed limits
+    pub speed: f32,
+    /// factor of the steering force
+    pub agility: f32,
+}
+
+impl Default for SpeciesParams {
+    fn default() -> Self {
+        Self {
+            // turquoise
+            color: [0.25, 0.88, 0.82],
+            speed: 1.,
+            agility: 1.,
+        }
+    }
+}
+
+impl SpeciesParams {
+    pub fn color(&self) -> Color {
+        let [r, g, b] = self.color;
+        Color::rgb(r, g, b)
+    }
+}
+
+/// steer away from the neighbors to avoid, the closer the stronger, and
+/// towards the nearest one to chase
+pub struct Interspecies;
+
+impl SteeringBehavior for Interspecies {
+    fn steer(&self, boid: &Boid, neighbors: &[Boid], surroundings: &Surroundings) -> Vec3 {
+        let params = surroundings.params;
+        let mut away = Vec3::ZERO;
+        let mut prey: Option<Vec3> = None;
+        for other in neighbors {
+            let offset = other.position - boid.position;
+            match params.relation(boid.species, other.species) {
+                Relation::Avoid => away -= offset / offset.length_squared().max(1.),
+                Relation::Chase
+                    if prey.is_none_or(|p| offset.length_squared() < p.length_squared()) =>
+                {
+                    prey = Some(offset)
+                }
+                _ => {}
+            }
+        }
+        let chase = prey.map_or(Vec3::ZERO, |p| steer_towards(p, boid.velocity, params));
+        steer_towards(away, boid.velocity, params) + chase
+    }
+
+    fn weight(&self, params: &FlockParams) -> f32 {
+        params.species_weight
+    }
+}
+
+/// marks the boids whose species was drawn at random rather than asked for
+#[derive(Component)]
+pub(crate) struct RandomSpecies;
+
+/// spread the boids of a random species over the species again when their
+/// number changes, e.g. once the config file is loaded; the others only get a
+/// new species if theirs is gone
+pub(crate) fn redistribute_species(
+    params: Res<FlockParams>,
+    mut count: Local<usize>,
+    mut flock: ResMut<Boids>,
+    mut boids: Query<(&mut Boid, Has<RandomSpecies>)>,
+) {
+    if !params.is_changed() || params.species.len() == *count {
+        return;
+    }
+    *count = params.species.len();
+    for (mut boid, random) in &mut boids {
+        if random || boid.species.0 >= params.species.len() {
+            boid.species = flock.random_species(&params);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::Arena;
+
+    #[test]
+    fn species_avoid_and_chase_each_other() {
+        let params = FlockParams {
+            species: vec![SpeciesParams::default(); 3],
+            // 0 avoids 1 and chases 2, the others keep the default
+            relations: vec![vec![Relation::Flock, Relation::Avoid, Relation::Chase]],
+            ..default()
+        };
+        assert_eq!(params.relation(Species(1), Species(0)), Relation::Flock);
+        assert_eq!(params.relation(Species(0), Species(2)), Relation::Chase);
+
+        let arena = Arena::default();
+        let surroundings = Surroundings {
+            params: &params,
+            arena: &arena,
+            obstacles: &[],
+            predators: &[],
+            lure: None,
+        };
+        let boid = Boid::new(Vec3::ZERO, Vec3::Y * params.max_speed);
+        let other = |x: f32, species| Boid {
+            species: Species(species),
+            ..Boid::new(Vec3::new(x, 0., 0.), Vec3::Y)
+        };
+        let steer = |neighbors: &[Boid]| Interspecies.steer(&boid, neighbors, &surroundings);
+        assert!(steer(&[other(10., 1)]).x < 0.);
+        assert!(steer(&[other(10., 2)]).x > 0.);
+        // the nearest prey wins
+        assert!(steer(&[other(30., 2), other(-10., 2)]).x < 0.);
+        assert_eq!(steer(&[other(10., 0)]), Vec3::ZERO);
+    }
+
+    #[test]
+    fn only_random_or_missing_species_are_redistributed() {
+        let mut world = World::new();
+        world.insert_resource(Boids(crate::flock::Flock::with_seed(0)));
+        world.insert_resource(FlockParams {
+            species: vec![SpeciesParams::default(); 3],
+            ..default()
+        });
+        let chosen = world
+            .spawn(Boid {
+                species: Species(2),
+                ..Boid::new(Vec3::ZERO, Vec3::X)
+            })
+            .id();
+        let random: Vec<Entity> = (0..50)
+            .map(|_| {
+                let boid = Boid::new(Vec3::ZERO, Vec3::X);
+                world.spawn((boid, RandomSpecies)).id()
+            })
+            .collect();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(redistribute_species);
+        let species = |world: &World, entity| world.get::<Boid>(entity).unwrap().species;
+
+        schedule.run(&mut world);
+        assert_eq!(species(&world, chosen), Species(2));
+        assert!(random.iter().any(|&e| species(&world, e) != Species(0)));
+
+        world.resource_mut::<FlockParams>().species.truncate(2);
+        schedule.run(&mut world);
+        assert!(species(&world, chosen).0 < 2);
+        assert!(random.iter().all(|&e| species(&world, e).0 < 2));
+    }
+}
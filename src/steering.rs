@@ -0,0 +1,289 @@
+use bevy::math::Vec3;
+use bevy::prelude::{App, Resource};
+
+use crate::boundary::{Arena, EdgeAvoidance};
+use crate::flock::Boid;
+use crate::lure::{FollowLure, Lure};
+use crate::obstacle::{Obstacle, ObstacleAvoidance};
+use crate::params::FlockParams;
+use crate::predator::{Flee, Predator};
+use crate::species::{Interspecies, Relation};
+
+/// what a steering behavior sees of the world besides the boid and its neighbors
+pub struct Surroundings<'a> {
+    pub params: &'a FlockParams,
+    pub arena: &'a Arena,
+    pub obstacles: &'a [Obstacle],
+    pub predators: &'a [Predator],
+    pub lure: Option<Lure>,
+}
+
+/// a rule contributing to the acceleration of every boid
+///
+/// Implementations are registered in `SteeringBehaviors`, the flock sums their
+/// weighted forces every tick.
+pub trait SteeringBehavior: Send + Sync + 'static {
+    /// acceleration of `boid` caused by this rule, in units per second squared
+    fn steer(&self, boid: &Boid, neighbors: &[Boid], surroundings: &Surroundings) -> Vec3;
+
+    /// factor the result of `steer` is multiplied with
+    fn weight(&self, _params: &FlockParams) -> f32 {
+        1.
+    }
+}
+
+/// steering force turning `velocity` into the `desired` direction at full speed,
+/// limited to the maximum steering force
+pub fn steer_towards(desired: Vec3, velocity: Vec3, params: &FlockParams) -> Vec3 {
+    if desired == Vec3::ZERO {
+        return Vec3::ZERO;
+    }
+    let desired = desired.normalize() * params.max_speed;
+    (desired - velocity).clamp_length_max(params.max_force)
+}
+
+/// steer away from the neighbors that are too close, the closer the stronger
+pub struct Separation;
+
+impl SteeringBehavior for Separation {
+    fn steer(&self, boid: &Boid, neighbors: &[Boid], surroundings: &Surroundings) -> Vec3 {
+        let params = surroundings.params;
+        let separation_squared = params.separation_distance * params.separation_distance;
+        let away = neighbors
+            .iter()
+            .map(|b| {
+                let away = boid.position - b.position;
+                let length_squared = away.length_squared();
+                if length_squared < separation_squared {
+                    away / length_squared.max(1.0)
+                } else {
+                    Vec3::ZERO
+                }
+            })
+            .sum::<Vec3>();
+        steer_towards(away, boid.velocity, params)
+    }
+
+    fn weight(&self, params: &FlockParams) -> f32 {
+        params.separation_weight
+    }
+}
+
+/// the neighbors `boid` flocks with, as opposed to avoiding or chasing them
+fn flockmates<'a>(
+    boid: &'a Boid,
+    neighbors: &'a [Boid],
+    params: &'a FlockParams,
+) -> impl Iterator<Item = &'a Boid> {
+    neighbors
+        .iter()
+        .filter(|b| params.relation(boid.species, b.species) == Relation::Flock)
+}
+
+/// sum of `value` over the flockmates of `boid`, and their number
+fn sum_over_flockmates(
+    boid: &Boid,
+    neighbors: &[Boid],
+    params: &FlockParams,
+    value: impl Fn(&Boid) -> Vec3,
+) -> (Vec3, usize) {
+    flockmates(boid, neighbors, params).fold((Vec3::ZERO, 0), |(sum, count), b| {
+        (sum + value(b), count + 1)
+    })
+}
+
+/// steer towards the average heading of the neighbors of the same flock
+pub struct Alignment;
+
+impl SteeringBehavior for Alignment {
+    fn steer(&self, boid: &Boid, neighbors: &[Boid], surroundings: &Surroundings) -> Vec3 {
+        let params = surroundings.params;
+        let (sum, count) = sum_over_flockmates(boid, neighbors, params, |b| b.velocity);
+        if count == 0 {
+            return Vec3::ZERO;
+        }
+        let avg_vel = sum / count as f32;
+        steer_towards(avg_vel, boid.velocity, params)
+    }
+
+    fn weight(&self, params: &FlockParams) -> f32 {
+        params.alignment_weight
+    }
+}
+
+/// steer towards the center of the neighbors of the same flock
+pub struct Cohesion;
+
+impl SteeringBehavior for Cohesion {
+    fn steer(&self, boid: &Boid, neighbors: &[Boid], surroundings: &Surroundings) -> Vec3 {
+        let params = surroundings.params;
+        let (sum, count) = sum_over_flockmates(boid, neighbors, params, |b| b.position);
+        if count == 0 {
+            return Vec3::ZERO;
+        }
+        let avg_pos = sum / count as f32;
+        steer_towards(avg_pos - boid.position, boid.velocity, params)
+    }
+
+    fn weight(&self, params: &FlockParams) -> f32 {
+        params.cohesion_weight
+    }
+}
+
+/// the steering behaviors applied to the flock, in order
+#[derive(Resource)]
+pub struct SteeringBehaviors(Vec<Box<dyn SteeringBehavior>>);
+
+impl Default for SteeringBehaviors {
+    /// the classic Reynolds rules: separation, alignment and cohesion,
+    /// plus obstacle avoidance, fleeing from predators, keeping off the edges,
+    /// following the mouse and the relations between species
+    fn default() -> Self {
+        let mut behaviors = Self::empty();
+        behaviors
+            .add(Separation)
+            .add(Alignment)
+            .add(Cohesion)
+            .add(ObstacleAvoidance)
+            .add(Flee)
+            .add(EdgeAvoidance)
+            .add(FollowLure)
+            .add(Interspecies);
+        behaviors
+    }
+}
+
+impl SteeringBehaviors {
+    pub fn empty() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn add(&mut self, behavior: impl SteeringBehavior) -> &mut Self {
+        self.0.push(Box::new(behavior));
+        self
+    }
+
+    /// weighted sum of the forces of all behaviors
+    pub fn force(&self, boid: &Boid, neighbors: &[Boid], surroundings: &Surroundings) -> Vec3 {
+        self.0
+            .iter()
+            .map(|b| b.steer(boid, neighbors, surroundings) * b.weight(surroundings.params))
+            .sum()
+    }
+
+    /// weighted force of every behavior, in order; `force` is their sum
+    pub fn forces(
+        &self,
+        boid: &Boid,
+        neighbors: &[Boid],
+        surroundings: &Surroundings,
+    ) -> Vec<Vec3> {
+        self.0
+            .iter()
+            .map(|b| b.steer(boid, neighbors, surroundings) * b.weight(surroundings.params))
+            .collect()
+    }
+}
+
+/// registering steering behaviors on top of the ones of `BoidsPlugin`
+pub trait AddSteeringBehavior {
+    fn add_steering_behavior(&mut self, behavior: impl SteeringBehavior) -> &mut Self;
+}
+
+impl AddSteeringBehavior for App {
+    fn add_steering_behavior(&mut self, behavior: impl SteeringBehavior) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(SteeringBehaviors::default)
+            .add(behavior);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flock::BASE_DIRECTION;
+    use crate::species::Species;
+    use bevy::utils::default;
+
+    const ARENA: Arena = Arena {
+        half_size: Vec3::new(500., 250., 0.),
+    };
+
+    fn surroundings(params: &FlockParams) -> Surroundings<'_> {
+        Surroundings {
+            params,
+            arena: &ARENA,
+            obstacles: &[],
+            predators: &[],
+            lure: None,
+        }
+    }
+
+    #[test]
+    fn steering_is_limited_by_max_force() {
+        let params = FlockParams::default();
+        let boid = Boid::new(Vec3::ZERO, BASE_DIRECTION * params.max_speed);
+        // a crowd right in front pushes back, but no harder than allowed
+        let crowd: Vec<Boid> = (1..10)
+            .map(|i| Boid::new(Vec3::new(0., i as f32, 0.), -boid.velocity))
+            .collect();
+        let force = SteeringBehaviors::default().force(&boid, &crowd, &surroundings(&params));
+        let weights = params.separation_weight + params.alignment_weight + params.cohesion_weight;
+        assert!(force.length() <= params.max_force * weights + 1e-3);
+        assert!(force.y < 0.);
+    }
+
+    #[test]
+    fn alignment_and_cohesion_only_follow_flockmates() {
+        let params = FlockParams {
+            species: vec![default(); 2],
+            relations: vec![vec![Relation::Flock, Relation::Avoid]],
+            ..default()
+        };
+        let boid = Boid::new(Vec3::ZERO, BASE_DIRECTION * params.max_speed);
+        let other = |species| Boid {
+            species: Species(species),
+            ..Boid::new(Vec3::new(20., 10., 0.), Vec3::X * params.max_speed)
+        };
+        let surroundings = surroundings(&params);
+        let steer = |behavior: &dyn SteeringBehavior, neighbor| {
+            behavior.steer(&boid, &[neighbor], &surroundings)
+        };
+        for behavior in [&Alignment as &dyn SteeringBehavior, &Cohesion] {
+            assert!(steer(behavior, other(0)).x > 0.);
+            assert_eq!(steer(behavior, other(1)), Vec3::ZERO);
+        }
+    }
+
+    #[test]
+    fn no_neighbors_no_force() {
+        let params = FlockParams::default();
+        let boid = Boid::new(Vec3::ZERO, BASE_DIRECTION * params.max_speed);
+        assert_eq!(
+            SteeringBehaviors::default().force(&boid, &[], &surroundings(&params)),
+            Vec3::ZERO
+        );
+    }
+
+    #[test]
+    fn custom_behaviors_are_added_to_the_sum() {
+        struct Up;
+        impl SteeringBehavior for Up {
+            fn steer(&self, _: &Boid, _: &[Boid], _: &Surroundings) -> Vec3 {
+                Vec3::Y
+            }
+            fn weight(&self, _: &FlockParams) -> f32 {
+                2.
+            }
+        }
+        let params = FlockParams::default();
+        let boid = Boid::new(Vec3::ZERO, BASE_DIRECTION * params.max_speed);
+        let mut behaviors = SteeringBehaviors::empty();
+        behaviors.add(Up).add(Up);
+        assert_eq!(
+            behaviors.force(&boid, &[], &surroundings(&params)),
+            Vec3::Y * 4.
+        );
+    }
+}
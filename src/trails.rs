@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::boundary::Arena;
+use crate::flock::Boid;
+use crate::params::FlockParams;
+use crate::plugin::step_boids;
+
+/// whether the boids leave trails, and how long they are
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct TrailSettings {
+    pub enabled: bool,
+    /// number of past positions kept, one per simulation tick
+    pub length: usize,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            length: 30,
+        }
+    }
+}
+
+/// the last positions of a boid, oldest first
+#[derive(Component, Default)]
+pub struct Trail(VecDeque<Vec3>);
+
+impl Trail {
+    /// add the newest position, dropping the oldest ones beyond `length`; a
+    /// jump farther than `max_jump`, i.e. wrapping around the arena, starts over
+    pub fn push(&mut self, position: Vec3, length: usize, max_jump: f32) {
+        if let Some(&last) = self.0.back() {
+            if last == position {
+                // paused
+                return;
+            }
+            if last.distance(position) > max_jump {
+                self.0.clear();
+            }
+        }
+        self.0.push_back(position);
+        while self.0.len() > length {
+            self.0.pop_front();
+        }
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &Vec3> {
+        self.0.iter()
+    }
+}
+
+fn add_trails(boids: Query<Entity, Added<Boid>>, mut commands: Commands) {
+    for entity in &boids {
+        commands.entity(entity).insert(Trail::default());
+    }
+}
+
+fn record_trails(
+    settings: Res<TrailSettings>,
+    arena: Res<Arena>,
+    mut boids: Query<(&Boid, &mut Trail)>,
+) {
+    if !settings.enabled {
+        return;
+    }
+    let max_jump = arena.half_size.truncate().min_element();
+    for (boid, mut trail) in &mut boids {
+        trail.push(boid.position, settings.length, max_jump);
+    }
+}
+
+/// trails take the color of their boid's species, the older points fade out
+fn draw_trails(
+    settings: Res<TrailSettings>,
+    params: Res<FlockParams>,
+    trails: Query<(&Trail, &Boid)>,
+    mut gizmos: Gizmos,
+) {
+    if !settings.enabled {
+        return;
+    }
+    for (trail, boid) in &trails {
+        let color = params.species_params(boid.species).color();
+        let len = trail.0.len() as f32;
+        gizmos.linestrip_gradient(
+            trail
+                .points()
+                .enumerate()
+                .map(|(i, &point)| (point, color.with_a((i + 1) as f32 / len))),
+        );
+    }
+}
+
+/// show or hide the trails with the T key; they start over when shown again
+fn toggle_trails(
+    keys: Res<Input<KeyCode>>,
+    mut settings: ResMut<TrailSettings>,
+    mut trails: Query<&mut Trail>,
+) {
+    if keys.just_pressed(KeyCode::T) {
+        settings.enabled = !settings.enabled;
+        for mut trail in &mut trails {
+            trail.0.clear();
+        }
+    }
+}
+
+/// fading trails behind the boids
+pub struct TrailsPlugin;
+
+impl Plugin for TrailsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrailSettings>()
+            .add_systems(FixedUpdate, record_trails.after(step_boids))
+            .add_systems(Update, (add_trails, toggle_trails, draw_trails));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trails_keep_the_last_positions_and_break_on_jumps() {
+        let mut trail = Trail::default();
+        for x in 0..5 {
+            trail.push(Vec3::new(x as f32, 0., 0.), 3, 100.);
+        }
+        trail.push(Vec3::new(4., 0., 0.), 3, 100.);
+        let xs: Vec<f32> = trail.points().map(|p| p.x).collect();
+        assert_eq!(xs, vec![2., 3., 4.]);
+
+        trail.push(Vec3::new(-500., 0., 0.), 3, 100.);
+        assert_eq!(trail.points().count(), 1);
+    }
+}
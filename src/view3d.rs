@@ -0,0 +1,241 @@
+use std::f32::consts::TAU;
+
+use bevy::input::mouse::{MouseMotion, MouseWheel};
+use bevy::prelude::*;
+use bevy::render::render_resource::PrimitiveTopology;
+
+use crate::boundary::Arena;
+use crate::camera::zoom_factor;
+use crate::coloring::ColorMode;
+use crate::flock::Boid;
+use crate::params::FlockParams;
+use crate::predator::Predator;
+
+/// a flat shaded cone pointing along `BASE_DIRECTION`, centered on the origin
+pub fn cone(radius: f32, height: f32, segments: usize) -> Mesh {
+    let tip = Vec3::Y * height / 2.;
+    let center = -tip;
+    let rim = |i: usize| {
+        let angle = i as f32 / segments as f32 * TAU;
+        center + Vec3::new(angle.cos(), 0., angle.sin()) * radius
+    };
+    let mut positions = Vec::with_capacity(segments * 6);
+    let mut normals = Vec::with_capacity(segments * 6);
+    for i in 0..segments {
+        let (a, b) = (rim(i), rim(i + 1));
+        let side = (b - tip).cross(a - tip).normalize();
+        positions.extend([tip, b, a].map(|p| p.to_array()));
+        normals.extend([side.to_array(); 3]);
+        // the base, seen from below
+        positions.extend([center, a, b].map(|p| p.to_array()));
+        normals.extend([Vec3::NEG_Y.to_array(); 3]);
+    }
+    Mesh::new(PrimitiveTopology::TriangleList)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+}
+
+/// a camera circling around `focus`, dragged with the middle mouse button and
+/// zoomed with the wheel
+#[derive(Component)]
+pub struct OrbitCamera {
+    pub focus: Vec3,
+    pub radius: f32,
+    /// rotation around the vertical axis, in radians
+    pub yaw: f32,
+    /// rotation above (negative) or below (positive) the horizon, in radians
+    pub pitch: f32,
+}
+
+impl OrbitCamera {
+    fn transform(&self) -> Transform {
+        let rotation = Quat::from_euler(EulerRot::YXZ, self.yaw, self.pitch, 0.);
+        Transform::from_translation(self.focus + rotation * Vec3::Z * self.radius)
+            .looking_at(self.focus, Vec3::Y)
+    }
+}
+
+/// a camera looking at the arena from the front, slightly above, and some light
+fn add_camera(arena: Res<Arena>, mut commands: Commands) {
+    let orbit = OrbitCamera {
+        focus: Vec3::ZERO,
+        radius: arena.half_size.length() * 2.,
+        yaw: 0.,
+        pitch: -0.4,
+    };
+    commands.spawn((
+        Camera3dBundle {
+            transform: orbit.transform(),
+            ..default()
+        },
+        orbit,
+    ));
+    commands.spawn(DirectionalLightBundle {
+        transform: Transform::from_xyz(1., 2., 1.5).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 0.5,
+    });
+}
+
+/// meshes and materials shared by all boids and all predators, with a
+/// material for every species of boids
+#[derive(Resource)]
+struct Cones {
+    boid: (Handle<Mesh>, Vec<Handle<StandardMaterial>>),
+    predator: (Handle<Mesh>, Handle<StandardMaterial>),
+}
+
+impl Cones {
+    fn boid_material(&self, boid: &Boid) -> Handle<StandardMaterial> {
+        let materials = &self.boid.1;
+        materials[boid.species.0.min(materials.len() - 1)].clone()
+    }
+}
+
+fn add_cone_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(Cones {
+        boid: (
+            meshes.add(cone(3., 10., 8)),
+            vec![materials.add(Color::TURQUOISE.into())],
+        ),
+        predator: (
+            meshes.add(cone(6., 20., 8)),
+            materials.add(Color::ORANGE_RED.into()),
+        ),
+    });
+}
+
+/// give the new boids and predators a cone
+fn add_cones(
+    boids: Query<(Entity, &Boid), Added<Boid>>,
+    predators: Query<Entity, Added<Predator>>,
+    cones: Res<Cones>,
+    mut commands: Commands,
+) {
+    for (entity, boid) in &boids {
+        commands
+            .entity(entity)
+            .insert((cones.boid.0.clone(), cones.boid_material(boid)));
+    }
+    for entity in &predators {
+        commands.entity(entity).insert(cones.predator.clone());
+    }
+}
+
+/// keep a material for every species in its color, and the boids in the
+/// material of their species
+fn color_cones(
+    params: Res<FlockParams>,
+    mut cones: ResMut<Cones>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut boids: Query<(&Boid, &mut Handle<StandardMaterial>)>,
+) {
+    if params.is_changed() {
+        let colors: Vec<Color> = params.species.iter().map(|s| s.color()).collect();
+        let handles = &mut cones.boid.1;
+        handles.resize_with(colors.len().max(1), || {
+            materials.add(Color::TURQUOISE.into())
+        });
+        for (handle, color) in handles.iter().zip(colors) {
+            if let Some(material) = materials.get_mut(handle) {
+                material.base_color = color;
+            }
+        }
+    }
+    for (boid, mut material) in &mut boids {
+        let wanted = cones.boid_material(boid);
+        if *material != wanted {
+            *material = wanted;
+        }
+    }
+}
+
+fn orbit_camera(
+    buttons: Res<Input<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+    mut cameras: Query<(&mut OrbitCamera, &mut Transform)>,
+) {
+    let drag: Vec2 = motion.read().map(|m| m.delta).sum();
+    let zoom = zoom_factor(wheel.read());
+    for (mut orbit, mut transform) in &mut cameras {
+        if buttons.pressed(MouseButton::Middle) {
+            orbit.yaw -= drag.x * 0.005;
+            orbit.pitch = (orbit.pitch - drag.y * 0.005).clamp(-1.5, 1.5);
+        }
+        orbit.radius = (orbit.radius * zoom).max(10.);
+        *transform = orbit.transform();
+    }
+}
+
+/// the other color modes need the instanced 2D boids
+fn warn_color_mode(mode: Res<ColorMode>) {
+    if mode.is_changed() && *mode != ColorMode::Plain {
+        warn!("the {:?} colors are only shown in 2D", *mode);
+    }
+}
+
+/// outline of the arena, so that the volume can be seen
+fn draw_arena(arena: Res<Arena>, mut gizmos: Gizmos) {
+    gizmos.cuboid(
+        Transform::from_scale(arena.half_size * 2.),
+        Color::rgba(1., 1., 1., 0.3),
+    );
+}
+
+/// camera, light, meshes and arena outline of the 3D mode
+pub struct View3dPlugin;
+
+impl Plugin for View3dPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, (add_camera, add_cone_meshes))
+            .add_systems(
+                Update,
+                (
+                    add_cones,
+                    color_cones,
+                    warn_color_mode,
+                    orbit_camera,
+                    draw_arena,
+                ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    #[test]
+    fn cone_normals_point_outwards() {
+        let mesh = cone(3., 10., 8);
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("cone without positions");
+        };
+        let Some(VertexAttributeValues::Float32x3(normals)) =
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+        else {
+            panic!("cone without normals");
+        };
+        assert_eq!(positions.len(), 8 * 6);
+        for triangle in positions.chunks(3).zip(normals.chunks(3)) {
+            let (corners, normals) = triangle;
+            let [a, b, c] = [corners[0], corners[1], corners[2]].map(Vec3::from);
+            let middle = (a + b + c) / 3.;
+            // counter-clockwise seen from outside, like the normal says
+            let winding = (b - a).cross(c - a);
+            assert!(winding.dot(Vec3::from(normals[0])) > 0.);
+            assert!(middle.dot(Vec3::from(normals[0])) > 0.);
+        }
+    }
+}